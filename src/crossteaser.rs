@@ -1,3 +1,4 @@
+pub mod crossteaser_pattern_database;
 pub mod crossteaser_search;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -37,7 +38,7 @@ impl Color {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Direction {
     Up = 0,
     North = 1,
@@ -109,6 +110,8 @@ pub struct Piece {
 pub struct PieceSet {
     pieces: Vec<Piece>,
     rotations: Vec<[usize; 4]>,
+    // RODADAS MÍNIMAS ENTRE CADA PAR DE (CASILLA, PIEZA) CON UNA SOLA PIEZA EN EL TABLERO
+    distances: Vec<u8>,
 }
 
 impl PieceSet {
@@ -168,6 +171,68 @@ impl PieceSet {
         self.rotations[index][direction.to_index()]
     }
 
+    // LA PIEZA QUE QUEDA AL RODAR HACIA LA CASILLA DE AL LADO EN LA DIRECCIÓN d
+    fn roll(&self, index: usize, d: Direction) -> usize {
+        use Direction::*;
+        match d {
+            North | South => self.rotate(index, d),
+            East | West => self.rotate(index, d.opposite()),
+            _ => panic!("No se puede rotar así"),
+        }
+    }
+
+    /// The minimum number of rolls that takes a piece alone on the board from the cell
+    /// with index `from_cell` in orientation `from_piece` to `to_cell` in orientation
+    /// `to_piece`. Every move rolls one piece, so it is a lower bound for that piece
+    /// on a full board too.
+    pub fn rolling_distance(
+        &self,
+        from_cell: usize,
+        from_piece: usize,
+        to_cell: usize,
+        to_piece: usize,
+    ) -> u64 {
+        let states = 9 * self.pieces.len();
+        let from = from_cell * self.pieces.len() + from_piece;
+        let to = to_cell * self.pieces.len() + to_piece;
+        self.distances[from * states + to] as u64
+    }
+
+    fn compute_distances(&self) -> Vec<u8> {
+        let n = self.pieces.len();
+        let states = 9 * n;
+        let mut ret = vec![u8::MAX; states * states];
+
+        // UNA BÚSQUEDA EN ANCHURA DESDE CADA (CASILLA, PIEZA)
+        for from in 0..states {
+            let distances = &mut ret[from * states..(from + 1) * states];
+            let mut not_processed = std::collections::VecDeque::new();
+            distances[from] = 0;
+            not_processed.push_back(from);
+
+            while let Some(state) = not_processed.pop_front() {
+                let coords = Board::index_to_coords(state / n);
+                let board = Board::from_one_piece(self, coords, state % n);
+                for direction in Direction::posible_rotations().iter() {
+                    let to = direction.traslate(Board::coords_to_i8(coords));
+                    if to.0 < 0 || to.0 > 2 || to.1 < 0 || to.1 > 2 {
+                        continue;
+                    }
+                    let to = Board::coords_to_usize(to);
+                    let rolled = board.rotate(coords, *direction).unwrap();
+                    let next = Board::coords_to_index(to.0, to.1) * n
+                        + rolled.piece_from_coords(to.0, to.1);
+                    if distances[next] == u8::MAX {
+                        distances[next] = distances[state] + 1;
+                        not_processed.push_back(next);
+                    }
+                }
+            }
+        }
+
+        ret
+    }
+
     fn compute_pieces_from(piece: &Piece) -> Vec<Piece> {
         let mut ret: Vec<Piece> = Vec::new();
         let mut not_processed_pieces: Vec<Piece> = Vec::new();
@@ -198,10 +263,13 @@ impl PieceSet {
     pub fn from_piece<'b>(piece: &'b Piece) -> PieceSet {
         let pieces = Self::compute_pieces_from(piece);
         let rotations = Self::compute_rotations(&pieces);
-        PieceSet {
+        let mut piece_set = PieceSet {
             pieces: pieces,
             rotations: rotations,
-        }
+            distances: Vec::new(),
+        };
+        piece_set.distances = piece_set.compute_distances();
+        piece_set
     }
 }
 
@@ -436,6 +504,14 @@ impl<'a> Board<'a> {
             .collect()
     }
 
+    // CADA HIJO CON LA DIRECCIÓN EN LA QUE SE MOVIÓ EL HUECO
+    pub fn children_and_moves(&self) -> Vec<(Direction, Board<'a>)> {
+        self.children_and_directions()
+            .iter()
+            .map(|(board, direction)| (direction.opposite(), *board))
+            .collect()
+    }
+
     pub fn ascii_art(&self) -> [[char; 14]; 11] {
         let mut b = [[' '; 14]; 11];
 
@@ -591,14 +667,7 @@ impl<'a> Board<'a> {
         }
 
         let old_piece = self.piece_from_coords(coords.0, coords.1);
-
-        use Direction::*;
-
-        let piece = match d {
-            North | South => self.piece_set.rotate(old_piece, d),
-            East | West => self.piece_set.rotate(old_piece, d.opposite()),
-            _ => panic!("No se puede rotar así"),
-        };
+        let piece = self.piece_set.roll(old_piece, d);
 
         let mut pieces = self.pieces.clone();
         pieces[coords.0][coords.1] = Self::empty();
@@ -777,6 +846,49 @@ mod tests {
         assert!(board == Board::from_one_piece(&piece_set, (0, 0), piece_index));
     }
 
+    #[test]
+    fn rolling_distances() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let n = piece_set.get_number_of_pieces();
+
+        for from_cell in 0..9 {
+            for from_piece in 0..n {
+                assert_eq!(
+                    piece_set.rolling_distance(from_cell, from_piece, from_cell, from_piece),
+                    0
+                );
+                let mut reachable = 0;
+                for to_cell in 0..9 {
+                    for to_piece in 0..n {
+                        let distance =
+                            piece_set.rolling_distance(from_cell, from_piece, to_cell, to_piece);
+                        if distance < u8::MAX as u64 {
+                            reachable += 1;
+                        }
+                        assert_eq!(
+                            distance,
+                            piece_set.rolling_distance(to_cell, to_piece, from_cell, from_piece)
+                        );
+                    }
+                }
+                // CADA RODADA ES UN GIRO DE 90 GRADOS Y CAMBIA EL COLOR DE LA CASILLA EN UN
+                // TABLERO DE AJEDREZ: SOLO SE ALCANZA LA MITAD
+                assert_eq!(reachable, 9 * n / 2);
+            }
+        }
+
+        // UNA RODADA SIEMPRE GIRA LA PIEZA: SIN GIRAR NO SE LLEGA AL LADO EN UN PASO
+        let board = Board::from_one_piece(&piece_set, (0, 0), 0);
+        let rolled = board.rotate((0, 0), South).unwrap();
+        let from = Board::coords_to_index(0, 0);
+        let to = Board::coords_to_index(0, 1);
+        assert_eq!(
+            piece_set.rolling_distance(from, 0, to, rolled.piece_from_coords(0, 1)),
+            1
+        );
+        assert!(piece_set.rolling_distance(from, 0, to, 0) > 1);
+    }
+
     #[test]
     fn diffs_of_children() {
         let piece_set = PieceSet::from_piece(&Piece::seed());
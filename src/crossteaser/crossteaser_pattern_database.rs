@@ -0,0 +1,477 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::crossteaser::crossteaser_search::*;
+use crate::search::pattern_database::Heuristic;
+use crate::search::*;
+
+const MAGIC: &[u8; 5] = b"CTPDB";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 5 + 1 + 1 + 1 + 9 + 8;
+const UNKNOWN: u8 = u8::MAX;
+
+/// Exact distances to a goal board in an abstraction that only keeps `pieces` of the
+/// pieces, their cells and orientations, and the empty cell. The board does not compare
+/// the ids, so the pieces are interchangeable and the same table serves for any group
+/// of that many ids. Rolling a piece of the group costs one and rolling any other
+/// costs nothing, so the values of disjoint groups can be added. The table has
+/// `9 × C(9, pieces) × 24^pieces` bytes: 10MB for three pieces, 376MB for four.
+pub struct BoardPatternDatabase {
+    pieces: usize,
+    // LA PIEZA DE CADA CASILLA EN EL OBJETIVO
+    goal: [usize; 9],
+    orientations: usize,
+    // EL RANGO DE CADA CONJUNTO DE CASILLAS POR SU MÁSCARA DE BITS, Y LA MÁSCARA DE CADA RANGO
+    ranks: Vec<usize>,
+    masks: Vec<u16>,
+    distances: Vec<u8>,
+}
+
+impl std::fmt::Debug for BoardPatternDatabase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "BoardPatternDatabase {{ pieces: {}, size: {} }}",
+            self.pieces,
+            self.distances.len()
+        )
+    }
+}
+
+// LAS PIEZAS DEL GRUPO, CADA UNA CON SU CASILLA Y SU ORIENTACIÓN, ORDENADAS POR CASILLA
+type Pattern = Vec<(usize, usize)>;
+
+impl BoardPatternDatabase {
+    // LA TABLA SIN CALCULAR, CON TODAS LAS DISTANCIAS DESCONOCIDAS
+    fn empty_table(goal: &Board, pieces: usize) -> BoardPatternDatabase {
+        assert!(
+            (1..=8).contains(&pieces),
+            "Un patrón tiene de una a ocho piezas"
+        );
+        let mut ranks = vec![0; 1 << 9];
+        let mut masks = Vec::new();
+        for mask in 0..(1u16 << 9) {
+            if mask.count_ones() as usize == pieces {
+                ranks[mask as usize] = masks.len();
+                masks.push(mask);
+            }
+        }
+        let mut goal_pieces = [Board::empty(); 9];
+        for (cell, piece) in goal_pieces.iter_mut().enumerate() {
+            *piece = goal.piece_from_index(cell);
+        }
+        let orientations = goal.piece_set.get_number_of_pieces();
+        let size = 9 * masks.len() * orientations.pow(pieces as u32);
+        BoardPatternDatabase {
+            pieces,
+            goal: goal_pieces,
+            orientations,
+            ranks,
+            masks,
+            distances: vec![UNKNOWN; size],
+        }
+    }
+
+    fn encode(&self, empty: usize, pattern: &[(usize, usize)]) -> usize {
+        let mask = pattern.iter().fold(0, |mask, (cell, _)| mask | 1 << cell);
+        pattern.iter().fold(
+            empty * self.masks.len() + self.ranks[mask],
+            |index, (_, piece)| index * self.orientations + piece,
+        )
+    }
+
+    fn decode(&self, mut index: usize) -> (usize, Pattern) {
+        let mut pieces = vec![0; self.pieces];
+        for piece in pieces.iter_mut().rev() {
+            *piece = index % self.orientations;
+            index /= self.orientations;
+        }
+        let mask = self.masks[index % self.masks.len()];
+        let cells = (0..9).filter(|cell| mask & 1 << cell != 0);
+        (index / self.masks.len(), cells.zip(pieces).collect())
+    }
+
+    /// Computes the table for `goal` with a breadth first search from every abstract
+    /// goal: the pieces of the group in any of the cells of the goal, with the piece of
+    /// that cell, and the empty cell in its place.
+    pub fn build(goal: &Board, pieces: usize) -> BoardPatternDatabase {
+        let mut database = Self::empty_table(goal, pieces);
+        let goal_empty = (0..9).find(|cell| database.goal[*cell] == Board::empty());
+        let goal_empty = goal_empty.expect("Sin vacio");
+        let mut not_processed = VecDeque::new();
+        for mask in database.masks.iter() {
+            if mask & 1 << goal_empty != 0 {
+                continue;
+            }
+            let pattern: Pattern = (0..9)
+                .filter(|cell| mask & 1 << cell != 0)
+                .map(|cell| (cell, database.goal[cell]))
+                .collect();
+            let index = database.encode(goal_empty, &pattern);
+            database.distances[index] = 0;
+            not_processed.push_back(index);
+        }
+
+        // LOS MOVIMIENTOS SE DESHACEN, ASÍ QUE LAS DISTANCIAS DESDE EL OBJETIVO SON LAS DE VUELTA.
+        // LOS QUE NO CUESTAN VAN DELANTE PARA QUE LA COLA SIGA ORDENADA POR DISTANCIA
+        while let Some(index) = not_processed.pop_front() {
+            let distance = database.distances[index];
+            let (empty, pattern) = database.decode(index);
+            let empty_coords = Board::coords_to_i8(Board::index_to_coords(empty));
+            for direction in Direction::posible_rotations().iter() {
+                let from = direction.traslate(empty_coords);
+                if from.0 < 0 || from.0 > 2 || from.1 < 0 || from.1 > 2 {
+                    continue;
+                }
+                let from = Board::coords_to_usize(from);
+                let from = Board::coords_to_index(from.0, from.1);
+                let mut next = pattern.clone();
+                let cost = match next.iter_mut().find(|(cell, _)| *cell == from) {
+                    Some(moved) => {
+                        let rolled = goal.piece_set.roll(moved.1, direction.opposite());
+                        *moved = (empty, rolled);
+                        next.sort_unstable();
+                        1
+                    }
+                    None => 0,
+                };
+                let next = database.encode(from, &next);
+                if distance + cost < database.distances[next] {
+                    database.distances[next] = distance + cost;
+                    if cost == 0 {
+                        not_processed.push_front(next);
+                    } else {
+                        not_processed.push_back(next);
+                    }
+                }
+            }
+        }
+        database
+    }
+
+    pub fn pieces(&self) -> usize {
+        self.pieces
+    }
+
+    /// The rolls of the pieces with the given ids needed to take them to the goal. Every
+    /// move rolls only one piece, so it never exceeds the true distance.
+    pub fn distance(&self, board: &Board, ids: &[usize]) -> u64 {
+        debug_assert_eq!(
+            ids.len(),
+            self.pieces,
+            "El grupo no es del tamaño del patrón"
+        );
+        // LO MISMO QUE encode, SIN GUARDAR EL PATRÓN: LAS CASILLAS DEL GRUPO DAN LA MÁSCARA Y
+        // SUS PIEZAS, EN ORDEN DE CASILLA, LOS DÍGITOS DEL FINAL
+        let mut mask = 0;
+        let mut pieces = 0;
+        let mut scale = 1;
+        for cell in 0..9 {
+            if ids.contains(&board.piece_id_from_index(cell)) {
+                mask |= 1 << cell;
+                pieces = pieces * self.orientations + board.piece_from_index(cell);
+                scale *= self.orientations;
+            }
+        }
+        debug_assert_eq!(
+            (mask as u16).count_ones() as usize,
+            self.pieces,
+            "Falta alguna pieza del grupo"
+        );
+        let (x, y) = board.empty_coords();
+        let empty = Board::coords_to_index(x, y);
+        self.distances[(empty * self.masks.len() + self.ranks[mask]) * scale + pieces] as u64
+    }
+
+    /// Writes the table to a binary file, with a header that `load` checks.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.distances.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(self.pieces as u8);
+        bytes.push(self.orientations as u8);
+        for piece in self.goal.iter() {
+            bytes.push(if *piece == Board::empty() {
+                UNKNOWN
+            } else {
+                *piece as u8
+            });
+        }
+        bytes.extend_from_slice(&(self.distances.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.distances);
+        fs::write(path, bytes)
+    }
+
+    /// Reads a table written by `save`. It fails with `InvalidData` if the file is not a
+    /// table or was built for another goal.
+    pub fn load<P: AsRef<Path>>(path: P, goal: &Board) -> io::Result<BoardPatternDatabase> {
+        fn invalid(message: &str) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        }
+
+        let bytes = fs::read(path)?;
+        if bytes.len() < HEADER_LEN || &bytes[0..5] != MAGIC {
+            return Err(invalid("not a pattern database"));
+        }
+        if bytes[5] != VERSION {
+            return Err(invalid("unknown pattern database version"));
+        }
+        let pieces = bytes[6] as usize;
+        if !(1..=8).contains(&pieces) {
+            return Err(invalid("wrong number of pieces"));
+        }
+        let mut database = Self::empty_table(goal, pieces);
+        if bytes[7] as usize != database.orientations {
+            return Err(invalid("pattern database for another piece set"));
+        }
+        let same_goal = database
+            .goal
+            .iter()
+            .zip(bytes[8..17].iter())
+            .all(|(piece, byte)| {
+                (*piece == Board::empty() && *byte == UNKNOWN) || *piece == *byte as usize
+            });
+        if !same_goal {
+            return Err(invalid("pattern database for another goal"));
+        }
+        let mut len = [0; 8];
+        len.copy_from_slice(&bytes[17..HEADER_LEN]);
+        if u64::from_le_bytes(len) != database.distances.len() as u64
+            || bytes.len() != HEADER_LEN + database.distances.len()
+        {
+            return Err(invalid("truncated pattern database"));
+        }
+        database.distances.copy_from_slice(&bytes[HEADER_LEN..]);
+        Ok(database)
+    }
+}
+
+/// Pattern databases of the same goal combined in a heuristic. Every partition is a list
+/// of disjoint groups of piece ids whose distances are added, and the heuristic is the
+/// maximum over the partitions.
+#[derive(Debug)]
+pub struct PatternHeuristic {
+    databases: Vec<BoardPatternDatabase>,
+    partitions: Vec<Vec<Vec<usize>>>,
+}
+
+impl PatternHeuristic {
+    /// Every group needs a database with its number of pieces.
+    pub fn new(
+        databases: Vec<BoardPatternDatabase>,
+        partitions: Vec<Vec<Vec<usize>>>,
+    ) -> PatternHeuristic {
+        assert!(!databases.is_empty(), "Hace falta algún patrón");
+        assert!(
+            databases
+                .windows(2)
+                .all(|pair| pair[0].goal == pair[1].goal),
+            "Los patrones tienen que ser del mismo objetivo"
+        );
+        for partition in partitions.iter() {
+            let mut ids: Vec<usize> = partition.iter().flatten().cloned().collect();
+            let count = ids.len();
+            ids.sort_unstable();
+            ids.dedup();
+            assert_eq!(ids.len(), count, "Los grupos se solapan: {:?}", partition);
+            for group in partition.iter() {
+                assert!(
+                    databases.iter().any(|d| d.pieces == group.len()),
+                    "No hay patrón de {} piezas",
+                    group.len()
+                );
+            }
+        }
+        PatternHeuristic {
+            databases,
+            partitions,
+        }
+    }
+
+    // ADDITIVEHEURISTIC Y MAXHEURISTIC PRESTAN SUS PARTES, ASÍ QUE NO SE PUEDEN GUARDAR
+    // JUNTO A LAS BASES DE DATOS DE LAS QUE DEPENDEN: SE COMBINAN AQUÍ SIN RESERVAR MEMORIA
+    pub fn value(&self, board: &Board) -> u64 {
+        self.partitions
+            .iter()
+            .map(|partition| {
+                partition
+                    .iter()
+                    .map(|group| {
+                        self.databases
+                            .iter()
+                            .find(|d| d.pieces == group.len())
+                            .unwrap()
+                            .distance(board, group)
+                    })
+                    .sum()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn is_goal(&self, board: &Board) -> bool {
+        let goal = &self.databases[0].goal;
+        (0..9).all(|cell| board.piece_from_index(cell) == goal[cell])
+    }
+}
+
+impl<'a> Heuristic<Board<'a>> for PatternHeuristic {
+    fn heuristic(&self, board: &Board<'a>) -> u64 {
+        self.value(board)
+    }
+}
+
+/// Reaches the goal of the pattern databases, with them as heuristic.
+#[derive(Debug)]
+pub struct BoardSearchWithPatterns<'p> {
+    pub patterns: &'p PatternHeuristic,
+    pub max_depth: Option<u64>,
+}
+
+impl<'a, 'p> SearchInfo<Board<'a>> for BoardSearchWithPatterns<'p> {
+    type Action = Direction;
+
+    fn is_goal(&self, board: &Board<'a>) -> bool {
+        self.patterns.is_goal(board)
+    }
+
+    fn heuristic(&self, board: &Board<'a>) -> u64 {
+        self.patterns.value(board)
+    }
+
+    fn expand_state(&self, board: &Board<'a>) -> Vec<(Direction, Board<'a>)> {
+        board.children_and_moves()
+    }
+
+    fn max_depth(&self) -> Option<u64> {
+        self.max_depth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crossteaser::crossteaser_pattern_database::*;
+    use crate::crossteaser::crossteaser_search::tests::assert_heuristic_is_sound;
+    use crate::crossteaser::crossteaser_search::tests::distances_to_goal;
+    use crate::crossteaser::crossteaser_search::tests::scrambled_samples;
+    use crate::search::astar::*;
+    use crate::search::pattern_database::WithHeuristic;
+
+    fn singletons() -> Vec<Vec<usize>> {
+        (1..=8).map(|id| vec![id]).collect()
+    }
+
+    fn pairs() -> Vec<Vec<Vec<usize>>> {
+        vec![
+            vec![vec![1, 2], vec![3, 4], vec![5, 6], vec![7, 8]],
+            vec![vec![1, 8], vec![2, 3], vec![4, 5], vec![6, 7]],
+        ]
+    }
+
+    #[test]
+    fn pattern_databases_are_admissible() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let rolling = BoardSearchWithGoal {
+            goal: board,
+            max_depth: None,
+        };
+        let mut partitions = pairs();
+        partitions.push(singletons());
+        let patterns = PatternHeuristic::new(
+            vec![
+                BoardPatternDatabase::build(&board, 1),
+                BoardPatternDatabase::build(&board, 2),
+            ],
+            partitions,
+        );
+        assert_eq!(patterns.value(&board), 0);
+
+        let distances = distances_to_goal(&board, 10);
+        let mut better = 0;
+        for (current, distance) in distances.iter() {
+            let value = patterns.value(current);
+            assert!(value <= *distance);
+            // UNA PIEZA SOLA YA RUEDA AL MENOS LO QUE LA DISTANCIA DE RODADA
+            assert!(value >= rolling.heuristic(current));
+            if value > rolling.heuristic(current) {
+                better += 1;
+            }
+        }
+        println!("boards:{} better than rolling:{}", distances.len(), better);
+        assert!(better > 0);
+    }
+
+    #[test]
+    fn search_with_pattern_databases() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let rolling = BoardSearchWithGoal {
+            goal: board,
+            max_depth: None,
+        };
+        let patterns = PatternHeuristic::new(vec![BoardPatternDatabase::build(&board, 2)], pairs());
+        let search = BoardSearchWithPatterns {
+            patterns: &patterns,
+            max_depth: None,
+        };
+
+        let samples = scrambled_samples(&board, 10, 25);
+        assert_heuristic_is_sound(&search, &samples);
+
+        for step in (10..=40).step_by(10) {
+            let scrambled = scrambled_board(&board, step);
+            let optimal = a_star_search(scrambled, &rolling);
+            let result = a_star_search(scrambled, &search);
+            println!(
+                "step:{} cost:{} rolling:{} patterns:{}",
+                step, optimal.cost, optimal.stats.expanded, result.stats.expanded
+            );
+            assert_eq!(result.cost, optimal.cost);
+            assert_eq!(*result.goal().unwrap(), board);
+
+            // LO MISMO CON LA HEURÍSTICA AÑADIDA A LA BÚSQUEDA CON OBJETIVO
+            let with_heuristic = WithHeuristic {
+                delegate: &rolling,
+                heuristic: &patterns,
+            };
+            assert_eq!(a_star_search(scrambled, &with_heuristic).cost, optimal.cost);
+            assert!(patterns.value(&scrambled) <= optimal.cost);
+        }
+    }
+
+    #[test]
+    fn save_and_load_pattern_database() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let database = BoardPatternDatabase::build(&board, 2);
+        let path = std::env::temp_dir().join(format!("crossteaser-{}.pdb", std::process::id()));
+
+        database.save(&path).unwrap();
+        let loaded = BoardPatternDatabase::load(&path, &board).unwrap();
+        assert_eq!(loaded.pieces(), 2);
+        assert!(loaded.distances == database.distances);
+
+        // OTRO OBJETIVO, OTRO TAMAÑO O UN FICHERO QUE NO ES UNA TABLA
+        let other = Board::from_piece(&piece_set, 0);
+        assert_ne!(other, board);
+        let error = BoardPatternDatabase::load(&path, &other).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.pop();
+        fs::write(&path, &bytes).unwrap();
+        let error = BoardPatternDatabase::load(&path, &board).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        fs::write(&path, b"not a table").unwrap();
+        let error = BoardPatternDatabase::load(&path, &board).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        fs::remove_file(&path).unwrap();
+        let error = BoardPatternDatabase::load(&path, &board).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+}
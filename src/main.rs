@@ -1,5 +1,6 @@
 // https://www.quantamagazine.org/the-map-of-mathematics-20200213/
 use std::env;
+use std::time::Duration;
 
 pub mod crossteaser;
 pub mod ravioli;
@@ -7,7 +8,8 @@ pub mod search;
 
 use crate::crossteaser::crossteaser_search::*;
 use crate::search::astar::*;
-use crate::search::*;
+use crate::search::limits::SearchLimits;
+use crate::search::observer::ProgressReporter;
 
 
 fn estados_posibles() {
@@ -21,7 +23,7 @@ fn estados_posibles() {
 
 
 
-fn aproxima<'a>(goal: Board<'a>, board: Board<'a>, changes: u8, max_level: u64 ) -> Option<(Vec<Direction>,Board<'a>)> {
+fn aproxima<'a>(goal: Board<'a>, board: Board<'a>, changes: u8, max_level: u64, limits: &SearchLimits ) -> Option<(Vec<Direction>,Board<'a>)> {
 
     let search_some_changes = BoardSearchSomeChanges {
         goal: goal,
@@ -29,17 +31,17 @@ fn aproxima<'a>(goal: Board<'a>, board: Board<'a>, changes: u8, max_level: u64 )
         changes: changes,
     };
 
-    let (found, _, _) = a_star_search(board, &search_some_changes);
-    match found{
+    let result = a_star_search_with(board, &search_some_changes, &mut ProgressReporter, limits);
+    match result.goal(){
         None => {
             None
         }
         Some(found) => {
-            let to_root = root_path_state(&found);
+            let ret_board = found.clone_with_pieceset(goal.piece_set);
+            let to_root = result.path;
             println!("APROXIMACION ENCONTRADA:\n");
             to_root.iter().for_each( |b| println!("{}\n\n",b.ascii_art_string()) );
-            let moves = Board::infer_moves_to_empty_position(to_root);
-            let ret_board = found.borrow().state.clone_with_pieceset(goal.piece_set);
+            let moves = result.actions;
             Some(
                 (
                     moves,
@@ -59,8 +61,14 @@ fn soluciona_por_pasos<'a>(goal: Board<'a>, board: Board<'a>) -> bool {
     println!("{}\n\n\n\n", board.ascii_art_string());
     
     let max_level = 28;
+    // CADA BÚSQUEDA TIENE SU PROPIO PRESUPUESTO
+    let limits = SearchLimits {
+        max_time: Some(Duration::from_secs(600)),
+        max_expanded: Some(50_000_000),
+        ..SearchLimits::none()
+    };
 
-    let aproximacion = aproxima(goal,board,3,max_level);
+    let aproximacion = aproxima(goal,board,3,max_level,&limits);
     if aproximacion.is_none(){
         println!("No hay aproximación a 3");
         return false;
@@ -70,7 +78,7 @@ fn soluciona_por_pasos<'a>(goal: Board<'a>, board: Board<'a>) -> bool {
     println!("{}\n\n", aproximacion.1.ascii_art_string() );
 
 
-    let aproximacion = aproxima(goal,aproximacion.1,2,max_level);
+    let aproximacion = aproxima(goal,aproximacion.1,2,max_level,&limits);
     if aproximacion.is_none(){
         println!("No hay aproximación a 2");
         return false;
@@ -80,9 +88,9 @@ fn soluciona_por_pasos<'a>(goal: Board<'a>, board: Board<'a>) -> bool {
     println!("{}\n\n", aproximacion.1.ascii_art_string() );
 
     let diffs = goal.compute_difs(&aproximacion.1);
-    let moves = moves_for_changes(diffs.clone(), max_level);
+    let (outcome, moves) = moves_for_changes_with(diffs.clone(), max_level, &limits);
     if moves.is_none(){
-        println!("No hay movimientos para diferencias finales: {:?}", diffs );
+        println!("No hay movimientos para diferencias finales: {:?} {:?}", diffs, outcome );
         return false;
     }
 
@@ -1,10 +1,32 @@
+use std::collections::HashSet;
 use std::fmt::{Display, Formatter, Result};
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::time::{Duration, Instant};
 
-use crate::ravioli::O;
+use crate::search::limits::*;
+use crate::search::observer::*;
+use crate::search::open_list::TieBreak;
+use crate::search::tree::*;
 
+pub mod anytime;
 pub mod astar;
+pub mod bidirectional;
+pub mod greedy;
+pub mod hda;
+pub mod heuristic_check;
+pub mod idastar;
+pub mod iterative_deepening;
+pub mod limits;
+pub mod move_pruning;
+pub mod observer;
+pub mod open_list;
+pub mod pattern_database;
+pub mod sma;
+pub mod solutions;
+pub mod stepper;
+pub mod tree;
+pub mod uniform_cost;
 // pub mod astar_vec;
 
 fn simple_hash<T: Hash>(object: &T) -> u64 {
@@ -14,197 +36,538 @@ fn simple_hash<T: Hash>(object: &T) -> u64 {
 }
 
 pub trait SearchInfo<T: State>: std::fmt::Debug {
+    /// What takes a state to one of its successors. The searches return the actions
+    /// of the solution along with its states.
+    type Action: Clone + std::fmt::Debug;
+
     fn heuristic(&self, _state: &T) -> u64 {
         0
     }
     fn max_depth(&self) -> Option<u64> {
         None
     }
-    fn expand_state(&self, state: &T) -> Vec<T>;
+    fn step_cost(&self, _from: &T, _to: &T) -> u64 {
+        1
+    }
+    fn tie_break(&self) -> TieBreak {
+        TieBreak::HighestCost
+    }
+    /// The successors of a state, each one with the action that reaches it.
+    fn expand_state(&self, state: &T) -> Vec<(Self::Action, T)>;
     fn is_goal(&self, state: &T) -> bool;
 }
 
-#[derive(Debug)]
-pub struct SearchNode<'a, T: State> {
-    to_root: Option<O<SearchNode<'a, T>>>,
+/// The search functions are generic over the domain so its calls can be inlined. With
+/// this impl a reference, `&dyn SearchInfo<T>` included, is also a domain, so a search
+/// can be stored or delegated to by reference.
+impl<T: State, S: SearchInfo<T> + ?Sized> SearchInfo<T> for &S {
+    type Action = S::Action;
+
+    fn heuristic(&self, state: &T) -> u64 {
+        (**self).heuristic(state)
+    }
+    fn max_depth(&self) -> Option<u64> {
+        (**self).max_depth()
+    }
+    fn step_cost(&self, from: &T, to: &T) -> u64 {
+        (**self).step_cost(from, to)
+    }
+    fn tie_break(&self) -> TieBreak {
+        (**self).tie_break()
+    }
+    fn expand_state(&self, state: &T) -> Vec<(S::Action, T)> {
+        (**self).expand_state(state)
+    }
+    fn is_goal(&self, state: &T) -> bool {
+        (**self).is_goal(state)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchNode<T: State, A> {
+    parent: Option<NodeId>,
+    /// The action that reached this node from its parent. `None` in the root.
+    action: Option<A>,
     level: u64,
+    cost: u64,
+    heuristic: u64,
     pub state: T,
     pub cached_state_hash: u64,
-
-    // https://stackoverflow.com/questions/34028324/how-do-i-use-a-custom-comparator-function-with-btreeset
-    // https://stackoverflow.com/questions/35786878/how-can-i-implement-ord-when-the-comparison-depends-on-data-not-part-of-the-comp/35788530#35788530
-    search: &'a dyn SearchInfo<T>,
 }
 
-impl<'a, T: State + Display> Display for SearchNode<'a, T> {
+impl<T: State + Display, A> Display for SearchNode<T, A> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         write!(f, "{} - {}", self.level, self.state)
     }
 }
 
-impl<'a, T: State> SearchNode<'a, T> {
-    fn new_root(state: T, search: &'a dyn SearchInfo<T>) -> Self {
-        SearchNode {
-            to_root: None,
-            level: 0,
-            cached_state_hash: simple_hash(&state),
-            state: state,
-            search: search,
+impl<T: State, A> SearchNode<T, A> {
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn action(&self) -> Option<&A> {
+        self.action.as_ref()
+    }
+
+    pub fn level(&self) -> u64 {
+        self.level
+    }
+
+    /// Cost of the path from the root.
+    pub fn g(&self) -> u64 {
+        self.cost
+    }
+
+    /// Heuristic of the state, computed once when the node is created.
+    pub fn h(&self) -> u64 {
+        self.heuristic
+    }
+
+    pub fn f(&self) -> u64 {
+        self.cost + self.heuristic
+    }
+}
+
+/// Best node of a search stopped by its limits: the lowest heuristic, and the lowest
+/// cost among the same heuristic. The path is copied because the depth first
+/// algorithms forget the nodes they leave behind.
+struct BestNode<T, A> {
+    h: u64,
+    g: u64,
+    path: Vec<T>,
+    actions: Vec<A>,
+}
+
+fn keep_best<T: State, S: SearchInfo<T> + ?Sized>(
+    best: &mut Option<BestNode<T, S::Action>>,
+    tree: &SearchTree<T, S>,
+    id: NodeId,
+) {
+    let node = &tree[id];
+    if let Some(b) = best {
+        if (node.heuristic, node.cost) >= (b.h, b.g) {
+            return;
         }
     }
+    *best = Some(BestNode {
+        h: node.heuristic,
+        g: node.cost,
+        path: tree.root_path_state(id),
+        actions: tree.root_path_actions(id),
+    });
 }
 
 pub trait State: Eq + Hash + Clone {}
 
-pub fn new_child<'a, T: State>(node: &O<SearchNode<'a, T>>, new_state: T) -> SearchNode<'a, T> {
-    SearchNode {
-        to_root: Some(node.clone()),
-        level: node.borrow().level + 1,
-        cached_state_hash: simple_hash(&new_state),
-        state: new_state,
-        search: node.borrow().search,
+/// How a search ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchOutcome {
+    Found,
+    /// Every reachable state was explored without finding a goal.
+    Exhausted,
+    /// No goal was found, but some nodes were not expanded because of `max_depth`.
+    DepthLimit,
+    /// The search was stopped before it could finish.
+    BudgetExceeded,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchStats {
+    pub expanded: usize,
+    pub generated: usize,
+    pub peak_frontier: usize,
+    pub max_depth_reached: u64,
+    pub elapsed: Duration,
+}
+
+impl SearchStats {
+    fn expanded(&mut self, level: u64) {
+        self.expanded += 1;
+        self.max_depth_reached = self.max_depth_reached.max(level);
+    }
+
+    fn generated(&mut self, count: usize) {
+        self.generated += count;
+    }
+
+    fn frontier(&mut self, size: usize) {
+        self.peak_frontier = self.peak_frontier.max(size);
     }
 }
 
-pub fn root_path<'a, T: State>(node: &'a O<SearchNode<'a, T>>) -> Vec<O<SearchNode<'a, T>>> {
-    let mut ret: Vec<O<SearchNode<'a, T>>> = Vec::new();
-    let cloned = node.clone();
-    let mut option: Option<O<SearchNode<'a, T>>> = Some(cloned);
+/// What every search algorithm returns.
+#[derive(Debug, Clone)]
+pub struct SearchResult<T, A> {
+    pub outcome: SearchOutcome,
+    /// States from the root to the goal. When the budget was exceeded, from the root
+    /// to the best node found so far. Empty otherwise.
+    pub path: Vec<T>,
+    /// `actions[i]` takes `path[i]` to `path[i + 1]`.
+    pub actions: Vec<A>,
+    /// Cost of `path`.
+    pub cost: u64,
+    pub stats: SearchStats,
+}
+
+impl<T: State, A> SearchResult<T, A> {
+    fn from_path(
+        path: Option<(Vec<T>, Vec<A>, u64)>,
+        depth_limited: bool,
+        mut stats: SearchStats,
+        started: Instant,
+    ) -> Self {
+        stats.elapsed = started.elapsed();
+        let (outcome, path, actions, cost) = match path {
+            Some((path, actions, cost)) => (SearchOutcome::Found, path, actions, cost),
+            None if depth_limited => (SearchOutcome::DepthLimit, Vec::new(), Vec::new(), 0),
+            None => (SearchOutcome::Exhausted, Vec::new(), Vec::new(), 0),
+        };
+        SearchResult {
+            outcome,
+            path,
+            actions,
+            cost,
+            stats,
+        }
+    }
+
+    fn from_node<S: SearchInfo<T, Action = A> + ?Sized>(
+        tree: &SearchTree<T, S>,
+        found: Option<NodeId>,
+        depth_limited: bool,
+        stats: SearchStats,
+        started: Instant,
+    ) -> Self {
+        let path = found.map(|id| {
+            (
+                tree.root_path_state(id),
+                tree.root_path_actions(id),
+                tree[id].cost,
+            )
+        });
+        Self::from_path(path, depth_limited, stats, started)
+    }
+
+    fn budget_exceeded(best: Option<BestNode<T, A>>, stats: SearchStats, started: Instant) -> Self {
+        let best = best.map(|b| (b.path, b.actions, b.g));
+        let mut ret = Self::from_path(best, false, stats, started);
+        ret.outcome = SearchOutcome::BudgetExceeded;
+        ret
+    }
 
-    while option.is_some() {
-        let o = option.unwrap();
-        option = o.borrow().to_root.clone();
-        ret.push(o.clone());
+    pub fn is_found(&self) -> bool {
+        self.outcome == SearchOutcome::Found
     }
 
-    ret
+    pub fn goal(&self) -> Option<&T> {
+        if self.is_found() {
+            self.path.last()
+        } else {
+            None
+        }
+    }
+
+    /// Last state of `path`: the goal, or the best node when the budget was exceeded.
+    pub fn best(&self) -> Option<&T> {
+        self.path.last()
+    }
 }
 
-pub fn root_path_state<'a, T: State>(node: &'a O<SearchNode<'a, T>>) -> Vec<T> {
-    let path = root_path(node);
-    let mut ret: Vec<T> = path.iter().map(|o| o.borrow().state.clone()).collect();
-    ret.reverse();
-    ret
+/// How a depth first search bounded by a depth limit ended.
+enum DepthFirstEnd {
+    Found(NodeId),
+    /// Every node within the limit was explored. `cut_off` tells whether some node
+    /// was not expanded because of the limit.
+    Exhausted {
+        cut_off: bool,
+    },
+    /// The limits of the search were exceeded.
+    Stopped,
 }
 
-fn expand_node<'a, T: State>(node: &O<SearchNode<'a, T>>) -> Vec<O<SearchNode<'a, T>>> {
-    let search_data = node.borrow().search;
-    let childs = search_data.expand_state(&node.borrow().state);
-    childs
-        .iter()
-        .map(|c| new_child(&node, c.clone()))
-        .map(|c| O::new(c))
-        .collect()
+// UN NODO DEL CAMINO ACTUAL Y LOS HIJOS QUE LE QUEDAN POR VISITAR
+struct Frame {
+    node: NodeId,
+    children: std::vec::IntoIter<NodeId>,
+    // TAMAÑO DEL ÁRBOL TRAS AÑADIR LOS HIJOS: LO QUE HAYA DESPUÉS ES DE UN HERMANO YA RECORRIDO
+    explored: usize,
 }
 
-pub fn deep_first_search<'a, T: State + std::fmt::Debug>(
-    root: T,
-    search_data: &'a dyn SearchInfo<T>,
-) -> Option<O<SearchNode<'a, T>>> {
-    fn search<'a, T: State + std::fmt::Debug>(
-        current: &O<SearchNode<'a, T>>,
-    ) -> Option<O<SearchNode<'a, T>>> {
-        let state: &T = &current.borrow().state;
-
-        println!("level: {} state: {:?}", current.borrow().level, state);
-
-        let search_data = current.borrow().search;
-        if search_data.is_goal(state) {
-            return Some(current.clone());
+/// Depth first search with an explicit stack, so deep searches do not overflow the
+/// call stack. The states of the current path are kept in a set and the children
+/// already in it are skipped, which breaks the cycles. The tree only keeps the current
+/// path and the children of its nodes. The iterative deepening search reuses it,
+/// adding up the stats of every iteration.
+struct DepthFirst<'o, 'a, T: State, S: SearchInfo<T> + ?Sized> {
+    tree: SearchTree<'a, T, S>,
+    stats: SearchStats,
+    best: Option<BestNode<T, S::Action>>,
+    observer: &'o mut dyn SearchObserver<T>,
+    limits: &'o SearchLimits,
+    started: Instant,
+}
+
+impl<'o, 'a, T: State, S: SearchInfo<T> + ?Sized> DepthFirst<'o, 'a, T, S> {
+    fn new(
+        search_data: &'a S,
+        observer: &'o mut dyn SearchObserver<T>,
+        limits: &'o SearchLimits,
+    ) -> Self {
+        DepthFirst {
+            tree: SearchTree::new(search_data),
+            stats: SearchStats::default(),
+            best: None,
+            observer,
+            limits,
+            started: Instant::now(),
         }
+    }
+
+    /// Explores from `root` without expanding the nodes at depth `limit`.
+    fn search(&mut self, root: NodeId, limit: Option<u64>) -> DepthFirstEnd {
+        let mut stack: Vec<Frame> = Vec::new();
+        let mut on_path: HashSet<T> = HashSet::new();
+        let mut cut_off = false;
+
+        let mut next = Some(root);
+        loop {
+            if let Some(current) = next.take() {
+                let level = self.tree[current].level;
+
+                if self.tree.search().is_goal(&self.tree[current].state) {
+                    self.observer.goal_found(self.tree[current].observed());
+                    return DepthFirstEnd::Found(current);
+                }
 
-        match search_data.max_depth() {
-            Some(max) => {
-                if current.borrow().level >= max {
-                    return None;
+                if limit.is_some_and(|max| level >= max) {
+                    cut_off = true;
+                    self.observer.pruned_by_depth(self.tree[current].observed());
+                } else {
+                    keep_best(&mut self.best, &self.tree, current);
+                    if self
+                        .limits
+                        .exceeded(&self.stats, self.tree.len(), self.started)
+                    {
+                        return DepthFirstEnd::Stopped;
+                    }
+
+                    let children = self.tree.expand_and_push(current);
+                    self.stats.expanded(level);
+                    self.stats.generated(children.len());
+                    self.stats.frontier(level as usize + 1);
+                    self.observer.expanded(self.tree[current].observed());
+                    for child in children.iter() {
+                        self.observer.generated(self.tree[*child].observed());
+                    }
+                    report_progress(self.observer, &mut self.stats, self.started);
+
+                    on_path.insert(self.tree[current].state.clone());
+                    stack.push(Frame {
+                        node: current,
+                        children: children.into_iter(),
+                        explored: self.tree.len(),
+                    });
                 }
             }
-            None => {}
-        }
 
-        let children = expand_node(current);
-        for child in children {
-            let ret = search(&child);
-            if ret.is_some() {
-                return ret;
+            let frame = match stack.last_mut() {
+                Some(frame) => frame,
+                None => return DepthFirstEnd::Exhausted { cut_off },
+            };
+            match frame.children.next() {
+                Some(child) => {
+                    // EL SUBÁRBOL DEL HERMANO ANTERIOR YA ESTÁ RECORRIDO: SUS NODOS NO HACEN FALTA
+                    self.tree.truncate(frame.explored);
+                    // LOS CICLOS DENTRO DEL CAMINO ACTUAL NUNCA LLEVAN A UNA SOLUCIÓN MEJOR
+                    if !on_path.contains(&self.tree[child].state) {
+                        next = Some(child);
+                    }
+                }
+                None => {
+                    let node = frame.node;
+                    stack.pop();
+                    on_path.remove(&self.tree[node].state);
+                }
             }
         }
-        None
     }
+}
+
+/// Depth first search with an explicit stack. The states of the current path are not
+/// visited again, so cycles end, but the same state may be reached by other paths. The
+/// solution is the first one found, not the shortest.
+pub fn deep_first_search<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+) -> SearchResult<T, S::Action> {
+    deep_first_search_with(root, search_data, &mut NoObserver, &SearchLimits::none())
+}
+
+pub fn deep_first_search_with<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+    observer: &mut dyn SearchObserver<T>,
+    limits: &SearchLimits,
+) -> SearchResult<T, S::Action> {
+    let mut dfs = DepthFirst::new(search_data, observer, limits);
+    let root = dfs.tree.add_root(root);
+    dfs.stats.generated(1);
+    dfs.observer.generated(dfs.tree[root].observed());
+    let (found, depth_limited) = match dfs.search(root, search_data.max_depth()) {
+        DepthFirstEnd::Found(found) => (Some(found), false),
+        DepthFirstEnd::Exhausted { cut_off } => (None, cut_off),
+        DepthFirstEnd::Stopped => {
+            return SearchResult::budget_exceeded(dfs.best, dfs.stats, dfs.started)
+        }
+    };
+    SearchResult::from_node(&dfs.tree, found, depth_limited, dfs.stats, dfs.started)
+}
+
+/// Breadth first graph search: a state already generated is not queued again. The first
+/// goal found has the fewest moves. With `max_depth`, the nodes at that depth are
+/// checked but not expanded, and the search only ends with `DepthLimit` after the
+/// whole level has been checked.
+pub fn breadth_first_search<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+) -> SearchResult<T, S::Action> {
+    breadth_first_search_with(root, search_data, &mut NoObserver, &SearchLimits::none())
+}
+
+/// The closed set of the limits is the set of generated states.
+pub fn breadth_first_search_with<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+    observer: &mut dyn SearchObserver<T>,
+    limits: &SearchLimits,
+) -> SearchResult<T, S::Action> {
+    breadth_first(root, search_data, observer, limits, true)
+}
 
-    let root = SearchNode::new_root(root, search_data);
-    search(&O::new(root))
+/// Breadth first tree search: states are queued every time they are generated, so
+/// nothing is hashed but duplicates are expanded again. For domains whose `Hash` is
+/// expensive or not discriminating.
+pub fn breadth_first_tree_search<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+) -> SearchResult<T, S::Action> {
+    breadth_first_tree_search_with(root, search_data, &mut NoObserver, &SearchLimits::none())
 }
 
-pub fn breadth_first_search<'a, T: State + std::fmt::Debug>(
+/// The closed set of the limits is the queue.
+pub fn breadth_first_tree_search_with<T: State, S: SearchInfo<T> + ?Sized>(
     root: T,
-    search_data: &'a dyn SearchInfo<T>,
-) -> Option<O<SearchNode<'a, T>>> {
+    search_data: &S,
+    observer: &mut dyn SearchObserver<T>,
+    limits: &SearchLimits,
+) -> SearchResult<T, S::Action> {
+    breadth_first(root, search_data, observer, limits, false)
+}
+
+fn breadth_first<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+    observer: &mut dyn SearchObserver<T>,
+    limits: &SearchLimits,
+    graph_search: bool,
+) -> SearchResult<T, S::Action> {
     use std::collections::VecDeque;
 
-    let mut queue: VecDeque<O<SearchNode<T>>> = VecDeque::new();
+    let started = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut tree = SearchTree::new(search_data);
+    let mut queue: VecDeque<NodeId> = VecDeque::new();
+    let mut generated: HashSet<T> = HashSet::new();
+    let mut depth_limited = false;
+    let mut best = None;
+
+    if graph_search {
+        generated.insert(root.clone());
+    }
+    let root = tree.add_root(root);
+    stats.generated(1);
+    observer.generated(tree[root].observed());
+    queue.push_back(root);
 
-    fn search<'a, T: State + std::fmt::Debug>(
-        queue: &mut VecDeque<O<SearchNode<'a, T>>>,
-    ) -> Option<O<SearchNode<'a, T>>> {
-        while let Some(current_node) = queue.pop_back() {
-            let state = &current_node.borrow().state;
-            println!("level: {} state: {:?}", current_node.borrow().level, state);
+    while let Some(current) = queue.pop_front() {
+        let level = tree[current].level;
 
-            let search_data = current_node.borrow().search;
-            if search_data.is_goal(state) {
-                println!("found: state: {:?}", state);
-                return Some(current_node.clone());
-            }
+        if search_data.is_goal(&tree[current].state) {
+            observer.goal_found(tree[current].observed());
+            return SearchResult::from_node(&tree, Some(current), false, stats, started);
+        }
 
-            if let Some(max) = search_data.max_depth() {
-                if current_node.borrow().level >= max {
-                    println!("  max level reached:{}", max);
-                    return None;
-                }
+        // LA COLA ES FIFO: LOS NODOS DEL ÚLTIMO NIVEL SE COMPRUEBAN TODOS ANTES DE TERMINAR
+        if let Some(max) = search_data.max_depth() {
+            if level >= max {
+                depth_limited = true;
+                observer.pruned_by_depth(tree[current].observed());
+                continue;
             }
-            let children = expand_node(&current_node);
-            for child in children {
-                println!(" child: {:?}", child);
-                queue.push_front(child)
+        }
+
+        keep_best(&mut best, &tree, current);
+        let closed = if graph_search {
+            generated.len()
+        } else {
+            queue.len() + 1
+        };
+        if limits.exceeded(&stats, closed, started) {
+            return SearchResult::budget_exceeded(best, stats, started);
+        }
+
+        let children = tree.expand(current);
+        stats.expanded(level);
+        stats.generated(children.len());
+        observer.expanded(tree[current].observed());
+        for child in children {
+            observer.generated(child.observed());
+            if graph_search && !generated.insert(child.state.clone()) {
+                continue;
             }
+            queue.push_back(tree.push(child));
         }
-        None
+        stats.frontier(queue.len());
+        report_progress(observer, &mut stats, started);
     }
-
-    let root = SearchNode::new_root(root, search_data);
-    queue.push_back(O::new(root));
-    search(&mut queue)
+    SearchResult::from_node(&tree, None, depth_limited, stats, started)
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
 
     use crate::search::*;
-    use std::ops::Deref;
 
     impl State for Vec<i32> {}
 
-    impl Display for O<SearchNode<'_, Vec<i32>>> {
-        fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-            let v: &Vec<i32> = &self.borrow().state;
-            let v: Vec<String> = v.iter().map(|i| i.to_string()).collect();
-            write!(f, "O({})", v.join("-"))
-        }
+    fn node_string(tree: &SearchTree<Vec<i32>, DummySearch>, id: NodeId) -> String {
+        let v: Vec<String> = tree[id].state.iter().map(|i| i.to_string()).collect();
+        format!("O({})", v.join("-"))
+    }
+
+    fn nodes_string(tree: &SearchTree<Vec<i32>, DummySearch>, ids: &[NodeId]) -> String {
+        ids.iter()
+            .map(|id| node_string(tree, *id))
+            .collect::<Vec<String>>()
+            .join(" ")
     }
 
     #[derive(Debug)]
-    struct DummySearch {}
+    pub(crate) struct DummySearch {}
 
     impl SearchInfo<Vec<i32>> for DummySearch {
-        fn expand_state(&self, state: &Vec<i32>) -> Vec<Vec<i32>> {
+        // EL NÚMERO QUE SE AÑADE AL FINAL
+        type Action = i32;
+
+        fn expand_state(&self, state: &Vec<i32>) -> Vec<(i32, Vec<i32>)> {
             if state.len() < 4 {
                 [0, 1, 2, 3]
                     .iter()
                     .map(|i| {
                         let mut child = state.clone();
                         child.push(*i);
-                        child
+                        (*i, child)
                     })
                     .collect()
             } else {
@@ -220,27 +583,211 @@ mod tests {
     #[test]
     fn deep_first_search_test() {
         let root = vec![];
-        let goal = deep_first_search(root.clone(), &DummySearch {});
+        let result = deep_first_search(root.clone(), &DummySearch {});
 
-        assert!(goal.is_some());
-        let goal = goal.unwrap();
-        let path = root_path_state(&goal);
+        assert!(result.is_found());
+        let path = result.path;
         println!("{:?}", path);
-        assert!(path[0] == root)
+        assert!(path[0] == root);
+        assert_eq!(result.cost, 4);
+        assert_eq!(result.actions, vec![0, 1, 2, 3]);
+        assert_eq!(result.stats.max_depth_reached, 4);
     }
 
     #[test]
     fn breadth_first_search_test() {
         let root = vec![];
-        let goal = breadth_first_search(root.clone(), &DummySearch {});
+        let result = breadth_first_search(root.clone(), &DummySearch {});
 
-        assert!(goal.is_some());
+        assert!(result.is_found());
 
-        let goal = goal.unwrap();
-        let path = root_path_state(&goal);
+        let path = result.path;
         println!("{:?}", path);
         assert!(path[0] == root);
         assert!(DummySearch {}.is_goal(&path[path.len() - 1]));
+        // TODOS LOS NODOS HASTA EL NIVEL 3 Y LOS 27 DEL NIVEL 4 ANTERIORES AL OBJETIVO
+        assert_eq!(result.stats.expanded, 1 + 4 + 16 + 64 + 27);
+        assert_eq!(result.stats.generated, 1 + 4 + 16 + 64 + 256);
+    }
+
+    #[derive(Debug)]
+    struct DummyWithMaxDepth {
+        max_depth: u64,
+    }
+
+    impl SearchInfo<Vec<i32>> for DummyWithMaxDepth {
+        type Action = i32;
+
+        fn expand_state(&self, state: &Vec<i32>) -> Vec<(i32, Vec<i32>)> {
+            DummySearch {}.expand_state(state)
+        }
+        fn is_goal(&self, state: &Vec<i32>) -> bool {
+            DummySearch {}.is_goal(state)
+        }
+        fn max_depth(&self) -> Option<u64> {
+            Some(self.max_depth)
+        }
+    }
+
+    #[test]
+    fn breadth_first_finds_goal_at_max_depth() {
+        // EL OBJETIVO ES EL NODO 28 DEL NIVEL 4: LOS ANTERIORES DEL MISMO NIVEL NO LO OCULTAN
+        let search = DummyWithMaxDepth { max_depth: 4 };
+        for result in [
+            breadth_first_search(vec![], &search),
+            breadth_first_tree_search(vec![], &search),
+        ]
+        .iter()
+        {
+            assert!(result.is_found());
+            assert_eq!(result.actions, vec![0, 1, 2, 3]);
+            assert_eq!(result.stats.expanded, 1 + 4 + 16 + 64);
+        }
+    }
+
+    #[test]
+    fn breadth_first_checks_the_whole_last_level() {
+        let search = DummyWithMaxDepth { max_depth: 3 };
+        for result in [
+            breadth_first_search(vec![], &search),
+            breadth_first_tree_search(vec![], &search),
+        ]
+        .iter()
+        {
+            assert_eq!(result.outcome, SearchOutcome::DepthLimit);
+            assert!(result.path.is_empty());
+            assert_eq!(result.stats.expanded, 1 + 4 + 16);
+            assert_eq!(result.stats.generated, 1 + 4 + 16 + 64);
+        }
+    }
+
+    #[test]
+    fn breadth_first_on_board_at_max_depth() {
+        use crate::crossteaser::crossteaser_search::*;
+        use crate::search::astar::*;
+
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let scrambled = scrambled_board(&board, 16);
+        let moves = a_star_search(
+            scrambled,
+            &BoardSearchWithGoal {
+                goal: board,
+                max_depth: None,
+            },
+        )
+        .cost;
+
+        let at_limit = BoardSearchWithGoal {
+            goal: board,
+            max_depth: Some(moves),
+        };
+        let graph = breadth_first_search(scrambled, &at_limit);
+        let tree = breadth_first_tree_search(scrambled, &at_limit);
+        assert_eq!(graph.cost, moves);
+        assert_eq!(tree.cost, moves);
+        // CADA MOVIMIENTO SE PUEDE DESHACER: SIN CONJUNTO CERRADO SE REPITEN MUCHOS ESTADOS
+        println!("graph:{:?} tree:{:?}", graph.stats, tree.stats);
+        assert!(graph.stats.expanded < tree.stats.expanded);
+
+        let below_limit = BoardSearchWithGoal {
+            goal: board,
+            max_depth: Some(moves - 1),
+        };
+        let result = breadth_first_search(scrambled, &below_limit);
+        assert_eq!(result.outcome, SearchOutcome::DepthLimit);
+    }
+
+    // EL MISMO ÁRBOL QUE DummySearch, SIN NINGÚN OBJETIVO
+    #[derive(Debug)]
+    pub(crate) struct NoGoal {}
+
+    impl SearchInfo<Vec<i32>> for NoGoal {
+        type Action = i32;
+
+        fn expand_state(&self, state: &Vec<i32>) -> Vec<(i32, Vec<i32>)> {
+            DummySearch {}.expand_state(state)
+        }
+        fn is_goal(&self, _state: &Vec<i32>) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn search_accepts_dyn_and_references() {
+        let search = DummySearch {};
+        let dynamic: &dyn SearchInfo<Vec<i32>, Action = i32> = &search;
+        let by_value = breadth_first_search(vec![], &search);
+        let by_dyn = breadth_first_search(vec![], dynamic);
+        // CON EL impl PARA REFERENCIAS, UNA REFERENCIA A dyn TAMBIÉN ES UN DOMINIO
+        let by_reference = breadth_first_search(vec![], &dynamic);
+
+        assert_eq!(by_value.path, by_dyn.path);
+        assert_eq!(by_value.path, by_reference.path);
+        assert_eq!(by_value.stats.expanded, by_reference.stats.expanded);
+    }
+
+    #[test]
+    fn search_without_goal_is_exhausted() {
+        let result = deep_first_search(vec![], &NoGoal {});
+        assert_eq!(result.outcome, SearchOutcome::Exhausted);
+        assert!(result.path.is_empty());
+        assert!(result.goal().is_none());
+        // LOS 256 NODOS DEL NIVEL 4 TAMBIÉN SE EXPANDEN, PERO NO TIENEN HIJOS
+        assert_eq!(result.stats.expanded, 1 + 4 + 16 + 64 + 256);
+        assert_eq!(result.stats.generated, 1 + 4 + 16 + 64 + 256);
+    }
+
+    #[test]
+    fn deep_first_search_skips_cycles() {
+        use crate::search::astar::tests::*;
+
+        // SIN MIRAR EL CAMINO ACTUAL, S-A-S-A... NO TERMINARÍA NUNCA
+        let search = SearchInGraph {
+            edges: vec![
+                ('S', 'A', 1),
+                ('A', 'S', 1),
+                ('A', 'B', 1),
+                ('B', 'A', 1),
+                ('B', 'G', 1),
+            ],
+            heuristic: vec![],
+            goal: 'G',
+        };
+        let result = deep_first_search('S', &search);
+        assert_eq!(result.path, vec!['S', 'A', 'B', 'G']);
+        assert_eq!(result.actions, vec!['A', 'B', 'G']);
+    }
+
+    impl State for u64 {}
+
+    // UNA LÍNEA DE ESTADOS QUE SE PUEDE RECORRER EN LOS DOS SENTIDOS
+    #[derive(Debug)]
+    struct Line {
+        goal: u64,
+    }
+
+    impl SearchInfo<u64> for Line {
+        type Action = i8;
+
+        fn expand_state(&self, state: &u64) -> Vec<(i8, u64)> {
+            let mut ret = vec![(1, state + 1)];
+            if *state > 0 {
+                ret.push((-1, state - 1));
+            }
+            ret
+        }
+        fn is_goal(&self, state: &u64) -> bool {
+            *state == self.goal
+        }
+    }
+
+    #[test]
+    fn deep_first_search_does_not_overflow_the_stack() {
+        let result = deep_first_search(0, &Line { goal: 200_000 });
+        assert!(result.is_found());
+        assert_eq!(result.cost, 200_000);
+        assert_eq!(result.stats.max_depth_reached, 199_999);
     }
 
     #[test]
@@ -248,60 +795,58 @@ mod tests {
         let vec = vec![0];
         let children = DummySearch {}.expand_state(&vec);
         println!("{:?}", children);
-        assert!(children == vec![vec![0, 0], vec![0, 1], vec![0, 2], vec![0, 3]]);
+        assert!(
+            children
+                == vec![
+                    (0, vec![0, 0]),
+                    (1, vec![0, 1]),
+                    (2, vec![0, 2]),
+                    (3, vec![0, 3])
+                ]
+        );
     }
 
     #[test]
     fn expand_node_test() {
         let vec = vec![0];
-        let node = O::new(SearchNode::new_root(vec.clone(), &DummySearch {}));
-        let children = expand_node(&node);
+        let search = DummySearch {};
+        let mut tree = SearchTree::new(&search);
+        let node = tree.add_root(vec);
+        let children = tree.expand_and_push(node);
 
-        println!(
-            "{}",
-            children
-                .iter()
-                .map(|c| c.to_string())
-                .collect::<Vec<String>>()
-                .join(" ")
-        );
+        println!("{}", nodes_string(&tree, &children));
         assert!(children.len() == 4);
+        assert!(children.iter().all(|c| tree[*c].parent() == Some(node)));
 
-        let children = expand_node(&children[0]);
-        println!(
-            "{}",
-            children
-                .iter()
-                .map(|c| c.to_string())
-                .collect::<Vec<String>>()
-                .join(" ")
-        );
+        let children = tree.expand_and_push(children[0]);
+        println!("{}", nodes_string(&tree, &children));
         assert!(children.len() == 4);
+        assert_eq!(tree.len(), 9);
+        assert_eq!(tree[children[3]].state, vec![0, 0, 3]);
+        assert_eq!(tree[children[3]].level(), 2);
     }
 
     #[test]
     fn root_path_test() {
         let vec = vec![0];
-        let node = O::new(SearchNode::new_root(vec, &DummySearch {}));
-        let children = expand_node(&node);
-        let children = expand_node(&children[0]);
-        let children = expand_node(&children[0]);
-
-        let root_path = root_path(&children[0]);
-
-        println!(
-            "{}",
-            root_path
-                .iter()
-                .map(|c| c.to_string())
-                .collect::<Vec<String>>()
-                .join(" ")
-        );
-        assert!(root_path.len() == 4);
+        let search = DummySearch {};
+        let mut tree = SearchTree::new(&search);
+        let node = tree.add_root(vec);
+        let children = tree.expand_and_push(node);
+        let children = tree.expand_and_push(children[1]);
+        let children = tree.expand_and_push(children[2]);
 
-        let root: std::cell::Ref<SearchNode<Vec<i32>>> = root_path[root_path.len() - 1].borrow();
+        let root_path = tree.root_path(children[3]);
 
-        assert!(std::ptr::eq(root.deref(), node.borrow().deref()));
+        println!("{}", nodes_string(&tree, &root_path));
+        assert!(root_path.len() == 4);
+        assert_eq!(root_path[0], children[3]);
+        assert_eq!(root_path[root_path.len() - 1], node);
+
+        assert_eq!(
+            tree.root_path_state(children[3]),
+            vec![vec![0], vec![0, 1], vec![0, 1, 2], vec![0, 1, 2, 3]]
+        );
     }
 
     #[test]
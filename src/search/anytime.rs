@@ -0,0 +1,460 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::search::astar::weighted_f;
+use crate::search::observer::*;
+use crate::search::open_list::*;
+use crate::search::*;
+
+/// Weights used by the anytime search: it starts with `initial` and lowers it by
+/// `step` after every solution, down to 1.
+#[derive(Debug, Clone, Copy)]
+pub struct AnytimeWeights {
+    pub initial: f64,
+    pub step: f64,
+}
+
+impl Default for AnytimeWeights {
+    fn default() -> Self {
+        AnytimeWeights {
+            initial: 3.0,
+            step: 0.5,
+        }
+    }
+}
+
+/// A solution reported by the anytime search. Each one is cheaper than the previous.
+#[derive(Debug, Clone)]
+pub struct AnytimeSolution<T, A> {
+    pub path: Vec<T>,
+    pub actions: Vec<A>,
+    pub cost: u64,
+    /// Weight of the iteration that found it.
+    pub weight: f64,
+    /// `cost` is at most `suboptimality` times the optimal cost. 1 means optimal.
+    pub suboptimality: f64,
+    /// Time from the start of the search.
+    pub elapsed: Duration,
+}
+
+struct Ara<'o, 'a, T: State, S: SearchInfo<T> + ?Sized> {
+    tree: SearchTree<'a, T, S>,
+    // EL ÚNICO NODO DE CADA ESTADO GENERADO: CUANDO MEJORA SU COSTE SE CAMBIA DE PADRE
+    nodes: HashMap<T, NodeId>,
+    open: OpenList<T, NodeId>,
+    closed: HashSet<T>,
+    // EXPANDIDOS EN ESTA ITERACIÓN QUE HAN MEJORADO: SE VUELVEN A ABRIR EN LA SIGUIENTE
+    inconsistent: HashSet<T>,
+    incumbent: Option<NodeId>,
+    depth_limited: bool,
+    stats: SearchStats,
+    best: Option<BestNode<T, S::Action>>,
+    observer: &'o mut dyn SearchObserver<T>,
+    limits: &'o SearchLimits,
+    started: Instant,
+}
+
+impl<'o, 'a, T: State, S: SearchInfo<T> + ?Sized> Ara<'o, 'a, T, S> {
+    fn incumbent_cost(&self) -> u64 {
+        self.incumbent.map_or(u64::MAX, |id| self.tree[id].g())
+    }
+
+    // EXPANDE MIENTRAS QUEDE ALGÚN NODO QUE PUEDA MEJORAR LA SOLUCIÓN CON ESTE PESO.
+    // DEVUELVE false SI SE SUPERAN LOS LÍMITES
+    fn improve_path(&mut self, weight: f64) -> bool {
+        while let Some((_, &current)) = self.open.peek() {
+            if self.open.f(&self.tree[current].state).unwrap() >= self.incumbent_cost() {
+                break;
+            }
+            let (state, current) = self.open.pop().unwrap();
+            let search_data = self.tree.search();
+
+            if search_data.is_goal(&state) {
+                if self.tree[current].g() < self.incumbent_cost() {
+                    self.observer.goal_found(self.tree[current].observed());
+                    self.incumbent = Some(current);
+                }
+                continue;
+            }
+
+            let level = self.tree[current].level();
+            if let Some(max) = search_data.max_depth() {
+                if level >= max {
+                    self.depth_limited = true;
+                    self.observer.pruned_by_depth(self.tree[current].observed());
+                    continue;
+                }
+            }
+
+            keep_best(&mut self.best, &self.tree, current);
+            if self
+                .limits
+                .exceeded(&self.stats, self.nodes.len(), self.started)
+            {
+                return false;
+            }
+
+            let children = self.tree.expand(current);
+            self.stats.expanded(level);
+            self.stats.generated(children.len());
+            self.observer.expanded(self.tree[current].observed());
+            self.closed.insert(state);
+
+            for child in children {
+                self.observer.generated(child.observed());
+                let f = weighted_f(child.g(), child.h(), weight);
+                let id = match self.nodes.get(&child.state) {
+                    Some(&id) => {
+                        if self.tree[id].g() <= child.g() {
+                            continue;
+                        }
+                        self.tree.reparent(id, &child);
+                        id
+                    }
+                    None => {
+                        let state = child.state.clone();
+                        let id = self.tree.push(child);
+                        self.nodes.insert(state, id);
+                        id
+                    }
+                };
+                let state = self.tree[id].state.clone();
+                let cost = self.tree[id].g();
+                if self.closed.contains(&state) {
+                    self.inconsistent.insert(state);
+                } else {
+                    self.open.push(state, f, cost, id);
+                }
+            }
+            self.stats.frontier(self.open.len());
+            report_progress(self.observer, &mut self.stats, self.started);
+        }
+        true
+    }
+
+    // LA SOLUCIÓN NO PUEDE SER PEOR QUE ESTE FACTOR: NINGÚN CAMINO PENDIENTE BAJA DE g + h
+    fn suboptimality(&self, weight: f64) -> f64 {
+        let pending = self
+            .open
+            .iter()
+            .map(|(_, id)| *id)
+            .chain(self.inconsistent.iter().map(|s| self.nodes[s]))
+            .map(|id| self.tree[id].f())
+            .min();
+        match pending {
+            Some(lower_bound) if lower_bound > 0 => {
+                weight.min(self.incumbent_cost() as f64 / lower_bound as f64)
+            }
+            Some(_) => weight,
+            None => 1.0,
+        }
+        .max(1.0)
+    }
+
+    // LOS PADRES PUEDEN HABER MEJORADO DESPUÉS DE CALCULAR g: EL CAMINO CUESTA g O MENOS
+    fn path_cost(&self, path: &[T]) -> u64 {
+        let search_data = self.tree.search();
+        path.windows(2)
+            .map(|pair| search_data.step_cost(&pair[0], &pair[1]))
+            .sum()
+    }
+
+    fn solution(&self, weight: f64) -> AnytimeSolution<T, S::Action> {
+        let goal = self.incumbent.unwrap();
+        let path = self.tree.root_path_state(goal);
+        AnytimeSolution {
+            cost: self.path_cost(&path),
+            path,
+            actions: self.tree.root_path_actions(goal),
+            weight,
+            suboptimality: self.suboptimality(weight),
+            elapsed: self.started.elapsed(),
+        }
+    }
+
+    // LOS INCONSISTENTES VUELVEN A LA FRONTERA Y TODAS LAS f SE CALCULAN CON EL NUEVO PESO
+    fn reweight(&mut self, weight: f64) {
+        let mut open = OpenList::new(self.tree.search().tie_break());
+        let pending: Vec<NodeId> = self
+            .open
+            .iter()
+            .map(|(_, id)| *id)
+            .chain(self.inconsistent.iter().map(|s| self.nodes[s]))
+            .collect();
+        for id in pending {
+            let node = &self.tree[id];
+            let f = weighted_f(node.g(), node.h(), weight);
+            open.push(node.state.clone(), f, node.g(), id);
+        }
+        self.open = open;
+        self.inconsistent.clear();
+        self.closed.clear();
+    }
+}
+
+/// Called with every solution the anytime search reports.
+pub type OnSolution<'a, T, A> = dyn FnMut(&AnytimeSolution<T, A>) + 'a;
+
+/// Anytime repairing A* (ARA*): a weighted A* with a high weight finds a solution
+/// quickly, and then the weight is lowered reusing the nodes already generated, each
+/// iteration reporting a cheaper solution through `on_solution`. The search ends when
+/// a solution is proved optimal, which is reported too even if it is not cheaper.
+/// With an admissible heuristic every solution comes with a bound on how far it is
+/// from the optimum.
+pub fn anytime_a_star_search<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+    weights: AnytimeWeights,
+    on_solution: &mut OnSolution<T, S::Action>,
+) -> SearchResult<T, S::Action> {
+    anytime_a_star_search_with(
+        root,
+        search_data,
+        weights,
+        on_solution,
+        &mut NoObserver,
+        &SearchLimits::none(),
+    )
+}
+
+/// When the limits are exceeded the result is the best solution found so far, if any,
+/// and `BudgetExceeded` with the best node otherwise. The closed set of the limits is
+/// every state generated.
+pub fn anytime_a_star_search_with<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+    weights: AnytimeWeights,
+    on_solution: &mut OnSolution<T, S::Action>,
+    observer: &mut dyn SearchObserver<T>,
+    limits: &SearchLimits,
+) -> SearchResult<T, S::Action> {
+    let mut ara = Ara {
+        tree: SearchTree::new(search_data),
+        nodes: HashMap::new(),
+        open: OpenList::new(search_data.tie_break()),
+        closed: HashSet::new(),
+        inconsistent: HashSet::new(),
+        incumbent: None,
+        depth_limited: false,
+        stats: SearchStats::default(),
+        best: None,
+        observer,
+        limits,
+        started: Instant::now(),
+    };
+    let root = ara.tree.add_root(root);
+    ara.stats.generated(1);
+    ara.observer.generated(ara.tree[root].observed());
+    ara.nodes.insert(ara.tree[root].state.clone(), root);
+    let mut weight = weights.initial.max(1.0);
+    let f = weighted_f(0, ara.tree[root].h(), weight);
+    ara.open.push(ara.tree[root].state.clone(), f, 0, root);
+
+    let mut stopped = false;
+    let mut reported: Option<(u64, f64)> = None;
+    loop {
+        if !ara.improve_path(weight) {
+            stopped = true;
+            break;
+        }
+        if ara.incumbent.is_some() {
+            let solution = ara.solution(weight);
+            let optimal = solution.suboptimality <= 1.0;
+            // SE AVISA CUANDO MEJORA EL COSTE, Y TAMBIÉN CUANDO SE DEMUESTRA QUE ES ÓPTIMA
+            let better = reported
+                .is_none_or(|(cost, bound)| solution.cost < cost || (optimal && bound > 1.0));
+            if better {
+                on_solution(&solution);
+                reported = Some((solution.cost, solution.suboptimality));
+            }
+            if optimal {
+                break;
+            }
+        }
+        if weight <= 1.0 || (ara.open.is_empty() && ara.inconsistent.is_empty()) {
+            break;
+        }
+        weight = (weight - weights.step).max(1.0);
+        ara.reweight(weight);
+    }
+
+    match ara.incumbent {
+        Some(goal) => {
+            let path = ara.tree.root_path_state(goal);
+            let cost = ara.path_cost(&path);
+            let actions = ara.tree.root_path_actions(goal);
+            SearchResult::from_path(Some((path, actions, cost)), false, ara.stats, ara.started)
+        }
+        None if stopped => SearchResult::budget_exceeded(ara.best, ara.stats, ara.started),
+        None => SearchResult::from_node(&ara.tree, None, ara.depth_limited, ara.stats, ara.started),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crossteaser::crossteaser_search::*;
+    use crate::search::anytime::*;
+    use crate::search::astar::tests::*;
+    use crate::search::astar::*;
+    use crate::search::limits::CancelToken;
+
+    fn scrambled_search<'a>(
+        piece_set: &'a PieceSet,
+        steps: usize,
+    ) -> (Board<'a>, HalfOfDifferences<'a>) {
+        let board = Board::from_piece(piece_set, piece_set.get_piece_index_of_initial_piece());
+        (
+            scrambled_board(&board, steps),
+            HalfOfDifferences { goal: board },
+        )
+    }
+
+    #[test]
+    fn anytime_solutions_improve_until_optimal() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let (scrambled, search) = scrambled_search(&piece_set, 40);
+        let optimal = a_star_search(scrambled, &search).cost;
+
+        let mut solutions = Vec::new();
+        let result = anytime_a_star_search(
+            scrambled,
+            &search,
+            AnytimeWeights {
+                initial: 4.0,
+                step: 1.0,
+            },
+            &mut |solution| solutions.push(solution.clone()),
+        );
+
+        for solution in solutions.iter() {
+            println!(
+                "w:{} cost:{} bound:{} {:?}",
+                solution.weight, solution.cost, solution.suboptimality, solution.elapsed
+            );
+            assert!(solution.cost as f64 <= solution.suboptimality * optimal as f64);
+            assert!(solution.suboptimality <= solution.weight);
+            assert_eq!(solution.actions.len(), solution.path.len() - 1);
+        }
+        assert!(solutions.windows(2).all(
+            |s| s[1].cost < s[0].cost || (s[1].cost == s[0].cost && s[1].suboptimality == 1.0)
+        ));
+        assert_eq!(solutions.last().unwrap().suboptimality, 1.0);
+        assert!(result.is_found());
+        assert_eq!(result.cost, optimal);
+    }
+
+    #[test]
+    fn anytime_with_goal_improves_until_optimal() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let search = BoardSearchWithGoal {
+            goal: board,
+            max_depth: None,
+        };
+        for step in (30..=40).step_by(10) {
+            let scrambled = scrambled_board(&board, step);
+            let optimal = a_star_search(scrambled, &search).cost;
+
+            let mut solutions = Vec::new();
+            let result = anytime_a_star_search(
+                scrambled,
+                &search,
+                AnytimeWeights {
+                    initial: 4.0,
+                    step: 1.0,
+                },
+                &mut |solution| solutions.push(solution.clone()),
+            );
+
+            for solution in solutions.iter() {
+                println!(
+                    "step:{} w:{} cost:{} bound:{}",
+                    step, solution.weight, solution.cost, solution.suboptimality
+                );
+                assert!(solution.cost as f64 <= solution.suboptimality * optimal as f64);
+                assert!(solution.suboptimality <= solution.weight);
+                assert_eq!(
+                    scrambled
+                        .apply_moves_to_empty_position_get_last(&solution.actions)
+                        .unwrap(),
+                    board
+                );
+            }
+            assert!(solutions
+                .windows(2)
+                .all(|s| s[1].cost < s[0].cost
+                    || (s[1].cost == s[0].cost && s[1].suboptimality == 1.0)));
+            assert_eq!(solutions.last().unwrap().suboptimality, 1.0);
+            assert_eq!(result.cost, optimal);
+        }
+    }
+
+    #[test]
+    fn anytime_finds_optimal_in_terrain() {
+        let search = SearchInTerrain {
+            terrain: vec![
+                vec![1, 1, 1, 1, 1],
+                vec![1, 9, 9, 9, 1],
+                vec![1, 9, 1, 9, 1],
+                vec![1, 9, 1, 9, 1],
+                vec![1, 1, 1, 1, 1],
+            ],
+            goal: Vector(2, 2),
+        };
+        let result = anytime_a_star_search(
+            Vector(0, 0),
+            &search,
+            AnytimeWeights::default(),
+            &mut |_| {},
+        );
+        assert_eq!(result.cost, 8);
+    }
+
+    #[test]
+    fn anytime_keeps_first_solution_when_stopped() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let (scrambled, search) = scrambled_search(&piece_set, 40);
+
+        let cancel = CancelToken::new();
+        let limits = SearchLimits {
+            cancel: Some(cancel.clone()),
+            ..SearchLimits::none()
+        };
+        let mut first = None;
+        let result = anytime_a_star_search_with(
+            scrambled,
+            &search,
+            AnytimeWeights {
+                initial: 5.0,
+                step: 0.5,
+            },
+            &mut |solution| {
+                first.get_or_insert(solution.cost);
+                cancel.cancel();
+            },
+            &mut NoObserver,
+            &limits,
+        );
+        assert!(result.is_found());
+        assert_eq!(Some(result.cost), first);
+    }
+
+    #[test]
+    fn anytime_without_solution() {
+        let search = SearchToGoal { goal: Vector(0, 0) };
+        let limits = SearchLimits {
+            max_expanded: Some(20),
+            ..SearchLimits::none()
+        };
+        let result = anytime_a_star_search_with(
+            Vector(1, 1),
+            &search,
+            AnytimeWeights::default(),
+            &mut |_| panic!("no hay solución"),
+            &mut NoObserver,
+            &limits,
+        );
+        assert_eq!(result.outcome, SearchOutcome::BudgetExceeded);
+        assert_eq!(result.stats.expanded, 20);
+    }
+}
@@ -1,150 +1,172 @@
-use std::cmp::Ordering::*;
-use std::cmp::{Eq, Ordering};
-use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::fmt::Display;
+use std::time::Instant;
 
-use crate::ravioli::O;
-
+use crate::search::observer::*;
+use crate::search::open_list::*;
 use crate::search::*;
 
-impl<'a, T: State + PartialEq> Eq for SearchNode<'a, T> {}
+impl<T: State + PartialEq, A> Eq for SearchNode<T, A> {}
 
-impl<'a, T: State + PartialEq> PartialEq for SearchNode<'a, T> {
+impl<T: State + PartialEq, A> PartialEq for SearchNode<T, A> {
     fn eq(&self, other: &Self) -> bool {
         self.state == other.state
     }
 }
 
-impl<'a, T: State + PartialEq> Ord for SearchNode<'a, T> {
-    fn cmp(&self, other: &Self) -> Ordering {
-        let search_data = self.search;
-        let mine = self.level + search_data.heuristic(&self.state);
-        let their = other.level + search_data.heuristic(&other.state);
-        match mine.cmp(&their) {
-            Equal => self.cached_state_hash.cmp(&other.cached_state_hash),
-            //            Equal => simple_hash(&self.state).cmp(&simple_hash(&other.state)),
-            others => others,
-        }
-    }
+pub fn a_star_search<T: State + Display, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+) -> SearchResult<T, S::Action> {
+    a_star_search_with(root, search_data, &mut NoObserver, &SearchLimits::none())
 }
 
-impl<'a, T: State + PartialEq> PartialOrd for SearchNode<'a, T> {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
+pub fn a_star_search_with<T: State + Display, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+    observer: &mut dyn SearchObserver<T>,
+    limits: &SearchLimits,
+) -> SearchResult<T, S::Action> {
+    weighted_a_star_search_with(root, search_data, 1.0, observer, limits)
 }
 
-fn pop<T: Ord + Clone>(set: &mut BTreeSet<T>) -> Option<T> {
-    let first = { set.iter().next().map(|f| f.clone()) };
-    match first {
-        Some(ret) => {
-            set.remove(&ret);
-            Some(ret)
-        }
-        None => None,
+/// f = g + w·h rounded down. With `weight` 1 it is A*.
+pub(crate) fn weighted_f(g: u64, h: u64, weight: f64) -> u64 {
+    if weight == 1.0 {
+        g + h
+    } else {
+        g + (weight * h as f64) as u64
     }
 }
 
-pub fn a_star_search<'a, T: State + PartialEq + Eq + Display>(
+/// A* ordering nodes by f = g + w·h. With an admissible heuristic and `weight` >= 1 the
+/// cost of the solution is at most `weight` times the optimal one, and it is usually
+/// found expanding far fewer nodes.
+pub fn weighted_a_star_search<T: State + Display, S: SearchInfo<T> + ?Sized>(
     root: T,
-    search_data: &'a dyn SearchInfo<T>,
-) -> (
-    Option<O<SearchNode<'a, T>>>,
-    BTreeSet<O<SearchNode<T>>>,
-    HashMap<T, O<SearchNode<T>>>,
-) {
-    let mut expanded_counter: usize = 0;
-    let root_node = O::new(SearchNode::new_root(root, search_data));
-
-    let mut max_visited_level = 0;
-
-    let mut not_expanded_nodes: BTreeSet<O<SearchNode<T>>> = BTreeSet::new();
-    let mut expanded_nodes: HashMap<T, O<SearchNode<T>>> = HashMap::new();
-
-    if search_data.is_goal(&root_node.borrow().state) {
-        // println!("  root es goal: {}", root_node.borrow().state );
-        return (Some(root_node), not_expanded_nodes, expanded_nodes);
-    }
-
-    not_expanded_nodes.insert(root_node);
+    search_data: &S,
+    weight: f64,
+) -> SearchResult<T, S::Action> {
+    weighted_a_star_search_with(
+        root,
+        search_data,
+        weight,
+        &mut NoObserver,
+        &SearchLimits::none(),
+    )
+}
 
-    while let Some(current) = pop(&mut not_expanded_nodes) {
-        let state = &current.borrow().state;
+pub fn weighted_a_star_search_with<T: State + Display, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+    weight: f64,
+    observer: &mut dyn SearchObserver<T>,
+    limits: &SearchLimits,
+) -> SearchResult<T, S::Action> {
+    let started = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut depth_limited = false;
+    let mut best = None;
+    let mut tree = SearchTree::new(search_data);
+    let root = tree.add_root(root);
+
+    let mut not_expanded_nodes: OpenList<T, NodeId> = OpenList::new(search_data.tie_break());
+    let mut expanded_nodes: HashMap<T, NodeId> = HashMap::new();
+
+    stats.generated(1);
+    observer.generated(tree[root].observed());
+    let f = weighted_f(0, tree[root].h(), weight);
+    not_expanded_nodes.push(tree[root].state.clone(), f, 0, root);
+
+    while let Some((state, current)) = not_expanded_nodes.pop() {
+        // EL OBJETIVO SE COMPRUEBA AL SACARLO, CON COSTES DISTINTOS DE 1 PUEDE HABER UN CAMINO MEJOR SIN EXPANDIR
+        if search_data.is_goal(&state) {
+            observer.goal_found(tree[current].observed());
+            let mut result =
+                SearchResult::from_node(&tree, Some(current), depth_limited, stats, started);
+            // UN ANTECESOR PUEDE HABERSE REENGANCHADO DESPUÉS DE CALCULAR LA g DEL OBJETIVO: EL
+            // CAMINO CUESTA g O MENOS
+            result.cost = result
+                .path
+                .windows(2)
+                .map(|pair| search_data.step_cost(&pair[0], &pair[1]))
+                .sum();
+            return result;
+        }
 
         match search_data.max_depth() {
             Some(max) => {
-                if current.borrow().level >= max {
+                if tree[current].level >= max {
+                    depth_limited = true;
+                    observer.pruned_by_depth(tree[current].observed());
                     continue;
                 }
             }
             None => {}
         }
 
-        if let Some(already_expanded) = expanded_nodes.get(state) {
-            //println!("  se expande de segundas: {}", state );
-            if already_expanded.borrow().level > current.borrow().level {
-                panic!("El que estaba sin expandir es más corto que el expandido");
-            }
-            continue;
+        keep_best(&mut best, &tree, current);
+        if limits.exceeded(&stats, expanded_nodes.len(), started) {
+            return SearchResult::budget_exceeded(best, stats, started);
         }
 
-        // println!("Expanding node: {}  heuristic:{}", &current.borrow(), current.borrow().search.heuristic(state) );
-
-        assert!(!search_data.is_goal(&state)); // Se debe detectar antes de meter en not_expanded_nodes
-        let children = expand_node(&current);
-        expanded_counter = expanded_counter + 1;
-        expanded_nodes.insert(state.clone(), current.clone());
-
-        if expanded_counter % 10000 == 0 {
-            println!(
-                "Nodos expandidos: {} Nodos sin expandir:{} Ultimo nivel:{}",
-                expanded_nodes.len(),
-                not_expanded_nodes.len(),
-                current.borrow().level
-            );
-        }
-
-        if current.borrow().level > max_visited_level {
-            max_visited_level = current.borrow().level;
-            // println!("Level:{}", max_visited_level);
-        }
+        let children = tree.expand(current);
+        stats.expanded(tree[current].level);
+        stats.generated(children.len());
+        observer.expanded(tree[current].observed());
+        expanded_nodes.insert(state, current);
 
         for child in children {
-            //println!("  child: {}", child.borrow() );
-
-            // IS GOAL?
-            if search_data.is_goal(&child.borrow().state) {
-                return (Some(child), not_expanded_nodes, expanded_nodes);
-            }
+            observer.generated(child.observed());
+            let child_cost = child.cost;
 
             // HAS BEEN ALREADY EXPANDED?
-            if let Some(already_expanded) = expanded_nodes.get(&child.borrow().state) {
-                if already_expanded.borrow().level > child.borrow().level {
-                    panic!("Hay que reenganchar el nodo existente con otro padre, se llegó por un sitio más corto");
+            // CON UNA HEURÍSTICA NO CONSISTENTE SE PUEDE LLEGAR POR UN SITIO MÁS CORTO: SE REENGANCHA
+            // EL NODO EXISTENTE CON EL NUEVO PADRE Y SE VUELVE A ABRIR
+            if let Some(&already_expanded) = expanded_nodes.get(&child.state) {
+                if tree[already_expanded].cost <= child_cost {
+                    continue;
                 }
+                expanded_nodes.remove(&child.state);
+                tree.reparent(already_expanded, &child);
+                let f = weighted_f(child_cost, child.h(), weight);
+                not_expanded_nodes.push(child.state, f, child_cost, already_expanded);
+                continue;
             }
 
+            let f = weighted_f(child_cost, child.h(), weight);
+
             // ALREADY IN NOT EXPANDED NODES?
-            // ADD TO not_expanded_nodes
-            if let Some(_already_in_not_expanded) = not_expanded_nodes.get(&child) {
-                // println!("  Ya estaba: {} {}", _already_in_not_expanded.borrow(), child.borrow() );
-            } else {
-                not_expanded_nodes.insert(child);
+            // SE QUEDA LA COPIA MÁS BARATA, Y SOLO ENTONCES SE GUARDA EN EL ÁRBOL
+            if let Some(&open) = not_expanded_nodes.get(&child.state) {
+                if not_expanded_nodes.decrease_key(&child.state, f, child_cost, open) {
+                    tree.reparent(open, &child);
+                }
+                continue;
             }
+
+            // ADD TO not_expanded_nodes
+            let state = child.state.clone();
+            let id = tree.push(child);
+            not_expanded_nodes.push(state, f, child_cost, id);
         }
+        stats.frontier(not_expanded_nodes.len());
+        report_progress(observer, &mut stats, started);
     }
 
-    (None, not_expanded_nodes, expanded_nodes)
+    SearchResult::from_node(&tree, None, depth_limited, stats, started)
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    // LOS MOVIMIENTOS EN LOS DOMINIOS DE Vector, CON LA y CRECIENDO HACIA EL SUR
+    use crate::crossteaser::Board;
+    pub(crate) use crate::crossteaser::Direction;
     use crate::search::astar::*;
     use std::fmt::*;
 
     #[derive(Clone, Hash, Debug, Eq, PartialEq)]
-    struct Vector(u64, u64);
+    pub(crate) struct Vector(pub u64, pub u64);
 
     impl Display for Vector {
         fn fmt(&self, f: &mut Formatter<'_>) -> Result {
@@ -155,11 +177,13 @@ mod tests {
     impl State for Vector {}
 
     #[derive(Debug)]
-    struct SearchToGoal {
-        goal: Vector,
+    pub(crate) struct SearchToGoal {
+        pub goal: Vector,
     }
 
     impl SearchInfo<Vector> for SearchToGoal {
+        type Action = Direction;
+
         fn heuristic(&self, state: &Vector) -> u64 {
             println!("Heuristica para {} con objetivo {}", state, self.goal);
             let dx = state.0 as i64 - self.goal.0 as i64;
@@ -168,23 +192,484 @@ mod tests {
             sqr.sqrt() as u64
         }
 
-        fn expand_state(&self, state: &Vector) -> Vec<Vector> {
-            vec![Vector(state.0 + 1, state.1), Vector(state.0, state.1 + 1)]
+        fn expand_state(&self, state: &Vector) -> Vec<(Direction, Vector)> {
+            vec![
+                (Direction::East, Vector(state.0 + 1, state.1)),
+                (Direction::South, Vector(state.0, state.1 + 1)),
+            ]
         }
         fn is_goal(&self, state: &Vector) -> bool {
             state.0 == self.goal.0 && state.1 == self.goal.1
         }
     }
 
+    // CADA CASILLA CUESTA LO QUE INDICA terrain[y][x] AL ENTRAR EN ELLA
+    #[derive(Debug)]
+    pub(crate) struct SearchInTerrain {
+        pub terrain: Vec<Vec<u64>>,
+        pub goal: Vector,
+    }
+
+    impl SearchInfo<Vector> for SearchInTerrain {
+        type Action = Direction;
+
+        fn heuristic(&self, state: &Vector) -> u64 {
+            let dx = state.0 as i64 - self.goal.0 as i64;
+            let dy = state.1 as i64 - self.goal.1 as i64;
+            (dx.abs() + dy.abs()) as u64
+        }
+
+        fn step_cost(&self, _from: &Vector, to: &Vector) -> u64 {
+            self.terrain[to.1 as usize][to.0 as usize]
+        }
+
+        fn expand_state(&self, state: &Vector) -> Vec<(Direction, Vector)> {
+            let mut ret = Vec::new();
+            if state.0 > 0 {
+                ret.push((Direction::West, Vector(state.0 - 1, state.1)));
+            }
+            if state.1 > 0 {
+                ret.push((Direction::North, Vector(state.0, state.1 - 1)));
+            }
+            if (state.0 as usize) < self.terrain[0].len() - 1 {
+                ret.push((Direction::East, Vector(state.0 + 1, state.1)));
+            }
+            if (state.1 as usize) < self.terrain.len() - 1 {
+                ret.push((Direction::South, Vector(state.0, state.1 + 1)));
+            }
+            ret
+        }
+
+        fn is_goal(&self, state: &Vector) -> bool {
+            *state == self.goal
+        }
+    }
+
     #[test]
     fn a_star_test() {
-        let (found, _, _) = a_star_search(Vector(0, 0), &SearchToGoal { goal: Vector(3, 4) });
-        assert!(found.is_some());
-        let goal = found.unwrap();
-        let path = root_path(&goal);
+        let result = a_star_search(Vector(0, 0), &SearchToGoal { goal: Vector(3, 4) });
+        assert!(result.is_found());
+        assert_eq!(result.cost, 7);
+
+        for state in result.path.iter() {
+            println!("{}", state);
+        }
+    }
+
+    impl State for char {}
 
-        for node in path {
-            println!("{}", node.borrow());
+    // GRAFO CON ARISTAS (ORIGEN, DESTINO, COSTE) Y UNA HEURÍSTICA ARBITRARIA POR ESTADO
+    #[derive(Debug)]
+    pub(crate) struct SearchInGraph {
+        pub edges: Vec<(char, char, u64)>,
+        pub heuristic: Vec<(char, u64)>,
+        pub goal: char,
+    }
+
+    impl SearchInfo<char> for SearchInGraph {
+        // LA ARISTA SE NOMBRA POR SU DESTINO
+        type Action = char;
+
+        fn heuristic(&self, state: &char) -> u64 {
+            self.heuristic
+                .iter()
+                .find(|(s, _)| s == state)
+                .map_or(0, |(_, h)| *h)
+        }
+
+        fn step_cost(&self, from: &char, to: &char) -> u64 {
+            self.edges
+                .iter()
+                .find(|(f, t, _)| f == from && t == to)
+                .unwrap()
+                .2
+        }
+
+        fn expand_state(&self, state: &char) -> Vec<(char, char)> {
+            self.edges
+                .iter()
+                .filter(|(f, _, _)| f == state)
+                .map(|(_, t, _)| (*t, *t))
+                .collect()
+        }
+
+        fn is_goal(&self, state: &char) -> bool {
+            *state == self.goal
+        }
+    }
+
+    // h(A) = 5 ES ADMISIBLE (A ESTÁ A 6 DEL OBJETIVO) PERO NO CONSISTENTE: h(S) = 0 Y S-A CUESTA 1.
+    // C SE EXPANDE PRIMERO DESDE B CON COSTE 4 Y LUEGO APARECE DESDE A CON COSTE 2
+    pub(crate) fn inconsistent_graph() -> SearchInGraph {
+        SearchInGraph {
+            edges: vec![
+                ('S', 'A', 1),
+                ('S', 'B', 3),
+                ('A', 'C', 1),
+                ('B', 'C', 1),
+                ('C', 'G', 5),
+            ],
+            heuristic: vec![('A', 5)],
+            goal: 'G',
+        }
+    }
+
+    // CADA MOVIMIENTO CAMBIA DOS CASILLAS: LA MITAD DE LAS DIFERENCIAS ES ADMISIBLE
+    #[derive(Debug)]
+    pub(crate) struct HalfOfDifferences<'a> {
+        pub goal: Board<'a>,
+    }
+
+    impl<'a> SearchInfo<Board<'a>> for HalfOfDifferences<'a> {
+        type Action = Direction;
+
+        fn heuristic(&self, board: &Board<'a>) -> u64 {
+            (self.goal.compute_difs(board).len() as u64).div_ceil(2)
+        }
+        fn expand_state(&self, board: &Board<'a>) -> Vec<(Direction, Board<'a>)> {
+            board.children_and_moves()
         }
+        fn is_goal(&self, board: &Board<'a>) -> bool {
+            *board == self.goal
+        }
+    }
+
+    #[test]
+    fn a_star_reopens_expanded_nodes() {
+        let search = inconsistent_graph();
+        let result = a_star_search('S', &search);
+        assert_eq!(result.cost, 7);
+        assert_eq!(result.path, vec!['S', 'A', 'C', 'G']);
+        // EL NODO REABIERTO CAMBIA DE PADRE Y TAMBIÉN DE ACCIÓN
+        assert_eq!(result.actions, vec!['A', 'C', 'G']);
+    }
+
+    #[test]
+    fn a_star_keeps_cheapest_not_expanded_copy() {
+        let search = SearchInGraph {
+            edges: vec![('S', 'A', 1), ('S', 'B', 5), ('A', 'B', 1), ('B', 'G', 1)],
+            heuristic: vec![],
+            goal: 'G',
+        };
+        let result = a_star_search('S', &search);
+        assert_eq!(result.cost, 3);
+        assert_eq!(result.path, vec!['S', 'A', 'B', 'G']);
+        assert_eq!(result.stats.expanded, 3);
+        assert_eq!(result.stats.generated, 5);
+    }
+
+    #[test]
+    fn heuristic_computed_once_per_node() {
+        use std::cell::Cell;
+
+        #[derive(Debug)]
+        struct CountingHeuristic {
+            delegate: SearchToGoal,
+            calls: Cell<usize>,
+        }
+
+        impl SearchInfo<Vector> for CountingHeuristic {
+            type Action = Direction;
+
+            fn heuristic(&self, state: &Vector) -> u64 {
+                self.calls.set(self.calls.get() + 1);
+                self.delegate.heuristic(state)
+            }
+            fn expand_state(&self, state: &Vector) -> Vec<(Direction, Vector)> {
+                self.delegate.expand_state(state)
+            }
+            fn is_goal(&self, state: &Vector) -> bool {
+                self.delegate.is_goal(state)
+            }
+        }
+
+        let search = CountingHeuristic {
+            delegate: SearchToGoal { goal: Vector(6, 8) },
+            calls: Cell::new(0),
+        };
+        let result = a_star_search(Vector(0, 0), &search);
+        assert_eq!(result.cost, 14);
+
+        // LA RAÍZ Y DOS HIJOS POR CADA NODO EXPANDIDO
+        assert_eq!(search.calls.get(), result.stats.generated);
+        assert_eq!(search.calls.get(), 1 + 2 * result.stats.expanded);
+
+        search.calls.set(0);
+        let mut tree = SearchTree::new(&search);
+        let root = tree.add_root(Vector(0, 0));
+        let children = tree.expand_and_push(root);
+        assert_eq!(search.calls.get(), 3);
+        for child in children.iter() {
+            let node = &tree[*child];
+            println!(
+                "{} g:{} h:{} f:{}",
+                node.state,
+                node.g(),
+                node.h(),
+                node.f()
+            );
+            assert_eq!(node.f(), node.g() + node.h());
+            assert_eq!(node.g(), node.level());
+        }
+        assert_eq!(search.calls.get(), 3);
+    }
+
+    #[test]
+    fn a_star_tie_breaks_find_same_cost() {
+        use crate::crossteaser::crossteaser_search::*;
+
+        #[derive(Debug)]
+        struct WithTieBreak<'a> {
+            delegate: BoardSearchWithGoal<'a>,
+            tie_break: TieBreak,
+        }
+
+        impl<'a> SearchInfo<Board<'a>> for WithTieBreak<'a> {
+            type Action = Direction;
+
+            fn expand_state(&self, board: &Board<'a>) -> Vec<(Direction, Board<'a>)> {
+                self.delegate.expand_state(board)
+            }
+            fn is_goal(&self, board: &Board<'a>) -> bool {
+                self.delegate.is_goal(board)
+            }
+            fn tie_break(&self) -> TieBreak {
+                self.tie_break
+            }
+        }
+
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let scrambled = scrambled_board(&board, 16);
+
+        let tie_breaks = [
+            TieBreak::HighestCost,
+            TieBreak::LowestCost,
+            TieBreak::Fifo,
+            TieBreak::Lifo,
+        ];
+        let costs: Vec<u64> = tie_breaks
+            .iter()
+            .map(|tie_break| {
+                let search = WithTieBreak {
+                    delegate: BoardSearchWithGoal {
+                        goal: board,
+                        max_depth: None,
+                    },
+                    tie_break: *tie_break,
+                };
+                let result = a_star_search(scrambled, &search);
+                println!("{:?}: expanded {}", tie_break, result.stats.expanded);
+                result.cost
+            })
+            .collect();
+        assert!(costs.iter().all(|c| *c == costs[0]));
+    }
+
+    // EL ALGORITMO CON LA FRONTERA EN UN BTreeSet, QUE CALCULABA LA HEURÍSTICA EN CADA COMPARACIÓN,
+    // SOLO SE CONSERVA PARA COMPARAR TIEMPOS
+    #[cfg(not(debug_assertions))]
+    fn btreeset_a_star_search<T: State, S: SearchInfo<T>>(root: T, search_data: &S) -> Option<u64> {
+        use std::cmp::Ordering;
+        use std::collections::BTreeSet;
+
+        struct ByF<'a, T: State, S> {
+            id: NodeId,
+            cost: u64,
+            state: T,
+            cached_state_hash: u64,
+            search: &'a S,
+        }
+
+        impl<'a, T: State, S: SearchInfo<T>> PartialEq for ByF<'a, T, S> {
+            fn eq(&self, other: &Self) -> bool {
+                self.cmp(other) == Ordering::Equal
+            }
+        }
+        impl<'a, T: State, S: SearchInfo<T>> Eq for ByF<'a, T, S> {}
+        impl<'a, T: State, S: SearchInfo<T>> PartialOrd for ByF<'a, T, S> {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl<'a, T: State, S: SearchInfo<T>> Ord for ByF<'a, T, S> {
+            fn cmp(&self, other: &Self) -> Ordering {
+                (
+                    self.cost + self.search.heuristic(&self.state),
+                    self.cached_state_hash,
+                )
+                    .cmp(&(
+                        other.cost + self.search.heuristic(&other.state),
+                        other.cached_state_hash,
+                    ))
+            }
+        }
+
+        fn by_f<'a, T: State, S: SearchInfo<T>>(
+            tree: &SearchTree<'a, T, S>,
+            id: NodeId,
+        ) -> ByF<'a, T, S> {
+            ByF {
+                id,
+                cost: tree[id].g(),
+                state: tree[id].state.clone(),
+                cached_state_hash: tree[id].cached_state_hash,
+                search: tree.search(),
+            }
+        }
+
+        let mut tree = SearchTree::new(search_data);
+        let mut not_expanded_nodes: BTreeSet<ByF<T, S>> = BTreeSet::new();
+        let mut not_expanded_index: HashMap<T, NodeId> = HashMap::new();
+        let mut expanded_nodes: HashMap<T, NodeId> = HashMap::new();
+
+        let root = tree.add_root(root);
+        not_expanded_index.insert(tree[root].state.clone(), root);
+        not_expanded_nodes.insert(by_f(&tree, root));
+
+        loop {
+            let current = not_expanded_nodes.pop_first()?.id;
+            let state = tree[current].state.clone();
+            not_expanded_index.remove(&state);
+            if search_data.is_goal(&state) {
+                return Some(tree[current].g());
+            }
+            let children = tree.expand_and_push(current);
+            expanded_nodes.insert(state, current);
+
+            for child in children {
+                let child_state = tree[child].state.clone();
+                let child_cost = tree[child].g();
+                if expanded_nodes.contains_key(&child_state) {
+                    continue;
+                }
+                if let Some(&already) = not_expanded_index.get(&child_state) {
+                    if tree[already].g() <= child_cost {
+                        continue;
+                    }
+                    not_expanded_nodes.remove(&by_f(&tree, already));
+                }
+                not_expanded_index.insert(child_state, child);
+                not_expanded_nodes.insert(by_f(&tree, child));
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_a_star_is_bounded() {
+        use crate::crossteaser::crossteaser_search::*;
+
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let scrambled = scrambled_board(&board, 40);
+        let search = HalfOfDifferences { goal: board };
+        let optimal = a_star_search(scrambled, &search);
+
+        let same = weighted_a_star_search(scrambled, &search, 1.0);
+        assert_eq!(same.cost, optimal.cost);
+        assert_eq!(same.stats.expanded, optimal.stats.expanded);
+
+        for weight in [1.5, 2.0, 3.0].iter() {
+            let result = weighted_a_star_search(scrambled, &search, *weight);
+            println!(
+                "w:{} cost:{} expanded:{} (a*: {} {})",
+                weight, result.cost, result.stats.expanded, optimal.cost, optimal.stats.expanded
+            );
+            // LA ÚNICA GARANTÍA ES LA COTA DEL COSTE: LOS EXPANDIDOS SOLO SE MUESTRAN
+            assert!(result.cost as f64 <= weight * optimal.cost as f64);
+            assert_eq!(
+                scrambled
+                    .apply_moves_to_empty_position_get_last(&result.actions)
+                    .unwrap(),
+                search.goal
+            );
+        }
+    }
+
+    #[test]
+    fn weighted_a_star_with_goal_is_bounded() {
+        use crate::crossteaser::crossteaser_search::*;
+
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let search = BoardSearchWithGoal {
+            goal: board,
+            max_depth: None,
+        };
+        for step in (20..=40).step_by(10) {
+            let scrambled = scrambled_board(&board, step);
+            let optimal = a_star_search(scrambled, &search);
+            for weight in [1.5, 2.0, 4.0].iter() {
+                let result = weighted_a_star_search(scrambled, &search, *weight);
+                println!(
+                    "step:{} w:{} cost:{} expanded:{} (a*: {} {})",
+                    step,
+                    weight,
+                    result.cost,
+                    result.stats.expanded,
+                    optimal.cost,
+                    optimal.stats.expanded
+                );
+                assert!(result.is_found());
+                assert!(result.cost as f64 <= weight * optimal.cost as f64);
+                // EL COSTE ES EL DEL CAMINO QUE SE DEVUELVE
+                let path_cost: u64 = result
+                    .path
+                    .windows(2)
+                    .map(|pair| search.step_cost(&pair[0], &pair[1]))
+                    .sum();
+                assert_eq!(result.cost, path_cost);
+                assert_eq!(
+                    scrambled
+                        .apply_moves_to_empty_position_get_last(&result.actions)
+                        .unwrap(),
+                    board
+                );
+            }
+        }
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[test]
+    fn open_list_against_btreeset() {
+        // ESTE TEST SOLO TIENE SENTIDO EN RELEASE
+        use crate::crossteaser::crossteaser_search::*;
+
+        fn measure_time<T>(msg: &str, function: &dyn Fn() -> T) -> (u128, T) {
+            let now = std::time::Instant::now();
+            let ret = function();
+            let millis = now.elapsed().as_millis();
+            println!("{}: {}", msg, millis);
+            (millis, ret)
+        }
+
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let search = HalfOfDifferences { goal: board };
+
+        let mut total_open_list = 0;
+        let mut total_btreeset = 0;
+        for step in (30..=50).step_by(5) {
+            let scrambled = scrambled_board(&board, step);
+
+            let (millis_open_list, cost_open_list) =
+                measure_time(&format!("open list, {} pasos", step), &|| {
+                    a_star_search(scrambled, &search).cost
+                });
+            let (millis_btreeset, cost_btreeset) =
+                measure_time(&format!("btreeset, {} pasos", step), &|| {
+                    btreeset_a_star_search(scrambled, &search).unwrap()
+                });
+
+            assert_eq!(cost_open_list, cost_btreeset);
+            total_open_list += millis_open_list;
+            total_btreeset += millis_btreeset;
+        }
+
+        // LOS TIEMPOS DEPENDEN DE LA CARGA DE LA MÁQUINA: SOLO SE MUESTRAN
+        println!(
+            "open list: {} btreeset: {}",
+            total_open_list, total_btreeset
+        );
     }
 }
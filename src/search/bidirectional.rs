@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+
+use crate::search::observer::*;
+use crate::search::*;
+
+/// Domains whose moves can be walked backwards, from the goal to the root.
+pub trait ReversibleSearchInfo<T: State>: SearchInfo<T> {
+    /// The states with a move to `state`, each one with the action that takes it to
+    /// `state`.
+    fn predecessors(&self, state: &T) -> Vec<(Self::Action, T)>;
+}
+
+impl<T: State, R: ReversibleSearchInfo<T> + ?Sized> ReversibleSearchInfo<T> for &R {
+    fn predecessors(&self, state: &T) -> Vec<(R::Action, T)> {
+        (**self).predecessors(state)
+    }
+}
+
+struct HalfSearch<T: State, A> {
+    layer: Vec<T>,
+    level: u64,
+    // ESTADO -> (ESTADO ANTERIOR EN ESTA MITAD Y LA ACCIÓN QUE LOS UNE, NIVEL). LA ACCIÓN
+    // SIEMPRE VA EN EL SENTIDO DE LA RAÍZ AL OBJETIVO
+    visited: HashMap<T, (Option<(T, A)>, u64)>,
+}
+
+// LO QUE COMPARTEN LAS DOS MITADES MIENTRAS DURA LA BÚSQUEDA
+struct Run<'o, T> {
+    stats: SearchStats,
+    observer: &'o mut dyn SearchObserver<T>,
+    limits: &'o SearchLimits,
+    started: Instant,
+}
+
+enum LayerEnd<T> {
+    Met(T),
+    Expanded,
+    Stopped,
+}
+
+impl<T: State, A: Clone> HalfSearch<T, A> {
+    fn new(state: T) -> Self {
+        let mut visited = HashMap::new();
+        visited.insert(state.clone(), (None, 0));
+        HalfSearch {
+            layer: vec![state],
+            level: 0,
+            visited,
+        }
+    }
+
+    fn expand_layer<F: Fn(&T) -> Vec<(A, T)>>(
+        &mut self,
+        neighbours: F,
+        other: &HalfSearch<T, A>,
+        run: &mut Run<T>,
+    ) -> LayerEnd<T> {
+        let layer = std::mem::take(&mut self.layer);
+        self.level += 1;
+        for (i, state) in layer.iter().enumerate() {
+            let closed = self.visited.len() + other.visited.len();
+            if run.limits.exceeded(&run.stats, closed, run.started) {
+                // LA CAPA SE DEJA COMO ESTABA PARA PODER ELEGIR EL MEJOR NODO
+                self.layer.extend(layer[i..].iter().cloned());
+                self.level -= 1;
+                return LayerEnd::Stopped;
+            }
+
+            let neighbours = neighbours(state);
+            run.stats.expanded(self.level - 1);
+            run.stats.generated(neighbours.len());
+            run.observer.expanded(observed(state, self.level - 1));
+            report_progress(run.observer, &mut run.stats, run.started);
+            for (action, neighbour) in neighbours {
+                run.observer.generated(observed(&neighbour, self.level));
+                if self.visited.contains_key(&neighbour) {
+                    continue;
+                }
+                self.visited.insert(
+                    neighbour.clone(),
+                    (Some((state.clone(), action)), self.level),
+                );
+                if other.visited.contains_key(&neighbour) {
+                    return LayerEnd::Met(neighbour);
+                }
+                self.layer.push(neighbour);
+            }
+        }
+        LayerEnd::Expanded
+    }
+
+    // LOS ESTADOS DESDE `state` HASTA EL ORIGEN DE ESTA MITAD, Y LAS ACCIONES ENTRE ELLOS
+    fn path_to(&self, state: &T) -> (Vec<T>, Vec<A>) {
+        let mut states = vec![state.clone()];
+        let mut actions = Vec::new();
+        let mut current = state;
+        while let Some((Some((previous, action)), _)) = self.visited.get(current) {
+            states.push(previous.clone());
+            actions.push(action.clone());
+            current = previous;
+        }
+        (states, actions)
+    }
+}
+
+// EN LA MITAD HACIA ATRÁS EL NIVEL ES LA DISTANCIA AL OBJETIVO
+fn observed<T>(state: &T, level: u64) -> ObservedNode<'_, T> {
+    ObservedNode {
+        state,
+        level,
+        g: level,
+        h: 0,
+    }
+}
+
+fn join_paths<T: State, A: Clone>(
+    forward: &HalfSearch<T, A>,
+    backward: &HalfSearch<T, A>,
+    meeting: &T,
+) -> (Vec<T>, Vec<A>) {
+    let (mut states, mut actions) = forward.path_to(meeting);
+    states.reverse();
+    actions.reverse();
+    let (backward_states, backward_actions) = backward.path_to(meeting);
+    states.extend(backward_states.into_iter().skip(1));
+    actions.extend(backward_actions);
+    (states, actions)
+}
+
+// EL MEJOR NODO ES EL DE MENOR HEURÍSTICA DE LA MITAD QUE SALE DE LA RAÍZ
+fn best_path<T: State, R: ReversibleSearchInfo<T> + ?Sized>(
+    forward: &HalfSearch<T, R::Action>,
+    search_data: &R,
+) -> (Vec<T>, Vec<R::Action>) {
+    let (best, _) = forward
+        .visited
+        .iter()
+        .min_by_key(|(state, (_, level))| (search_data.heuristic(state), *level))
+        .unwrap();
+    let (mut states, mut actions) = forward.path_to(best);
+    states.reverse();
+    actions.reverse();
+    (states, actions)
+}
+
+fn search<T: State, R: ReversibleSearchInfo<T> + ?Sized>(
+    root: T,
+    goal: T,
+    search_data: &R,
+    run: &mut Run<T>,
+) -> (SearchOutcome, (Vec<T>, Vec<R::Action>)) {
+    let mut forward = HalfSearch::new(root);
+    let mut backward = HalfSearch::new(goal);
+
+    while !forward.layer.is_empty() && !backward.layer.is_empty() {
+        if let Some(max) = search_data.max_depth() {
+            if forward.level + backward.level >= max {
+                for half in [&forward, &backward].iter() {
+                    for state in half.layer.iter() {
+                        run.observer.pruned_by_depth(observed(state, half.level));
+                    }
+                }
+                return (SearchOutcome::DepthLimit, (Vec::new(), Vec::new()));
+            }
+        }
+
+        let end = if forward.layer.len() <= backward.layer.len() {
+            forward.expand_layer(|s| search_data.expand_state(s), &backward, run)
+        } else {
+            backward.expand_layer(|s| search_data.predecessors(s), &forward, run)
+        };
+        match end {
+            LayerEnd::Met(meeting) => {
+                return (
+                    SearchOutcome::Found,
+                    join_paths(&forward, &backward, &meeting),
+                );
+            }
+            LayerEnd::Stopped => {
+                return (
+                    SearchOutcome::BudgetExceeded,
+                    best_path(&forward, search_data),
+                );
+            }
+            LayerEnd::Expanded => {}
+        }
+        run.stats
+            .frontier(forward.layer.len() + backward.layer.len());
+    }
+    (SearchOutcome::Exhausted, (Vec::new(), Vec::new()))
+}
+
+/// Breadth first search growing from `root` and from `goal` at the same time, always
+/// expanding the smaller layer, until both halves meet. Returns the states from
+/// `root` to `goal` of a path with the fewest moves. `step_cost` is not used: the
+/// cost of the result is the number of moves, and `max_depth_reached` is the deepest
+/// level of either half.
+pub fn bidirectional_search<T: State, R: ReversibleSearchInfo<T> + ?Sized>(
+    root: T,
+    goal: T,
+    search_data: &R,
+) -> SearchResult<T, R::Action> {
+    bidirectional_search_with(
+        root,
+        goal,
+        search_data,
+        &mut NoObserver,
+        &SearchLimits::none(),
+    )
+}
+
+/// The observer is told about the states of both halves. The closed set of the limits
+/// is the states visited by both halves, and the best node when they are exceeded is
+/// the one with the lowest heuristic reached from `root`.
+pub fn bidirectional_search_with<T: State, R: ReversibleSearchInfo<T> + ?Sized>(
+    root: T,
+    goal: T,
+    search_data: &R,
+    observer: &mut dyn SearchObserver<T>,
+    limits: &SearchLimits,
+) -> SearchResult<T, R::Action> {
+    let mut run = Run {
+        stats: SearchStats::default(),
+        observer,
+        limits,
+        started: Instant::now(),
+    };
+    run.stats.generated(2);
+    run.observer.generated(observed(&root, 0));
+    run.observer.generated(observed(&goal, 0));
+
+    let (outcome, (path, actions)) = if root == goal {
+        (SearchOutcome::Found, (vec![root], Vec::new()))
+    } else {
+        search(root, goal, search_data, &mut run)
+    };
+    let cost = path.len().saturating_sub(1) as u64;
+    if outcome == SearchOutcome::Found {
+        run.observer
+            .goal_found(observed(path.last().unwrap(), cost));
+    }
+    let mut stats = run.stats;
+    stats.elapsed = run.started.elapsed();
+    SearchResult {
+        outcome,
+        path,
+        actions,
+        cost,
+        stats,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crossteaser::crossteaser_search::*;
+    use crate::search::astar::tests::*;
+    use crate::search::astar::*;
+    use crate::search::bidirectional::*;
+
+    impl ReversibleSearchInfo<Vector> for SearchToGoal {
+        fn predecessors(&self, state: &Vector) -> Vec<(Direction, Vector)> {
+            let mut ret = Vec::new();
+            if state.0 > 0 {
+                ret.push((Direction::East, Vector(state.0 - 1, state.1)));
+            }
+            if state.1 > 0 {
+                ret.push((Direction::South, Vector(state.0, state.1 - 1)));
+            }
+            ret
+        }
+    }
+
+    #[test]
+    fn bidirectional_test() {
+        let search = SearchToGoal { goal: Vector(3, 4) };
+        let result = bidirectional_search(Vector(0, 0), Vector(3, 4), &search);
+        assert_eq!(result.cost, 7);
+        let path = result.path;
+        println!("{:?} {:?}", path, result.actions);
+        assert_eq!(path.len(), 8);
+        assert_eq!(result.actions.len(), 7);
+        assert_eq!(path[0], Vector(0, 0));
+        assert_eq!(path[path.len() - 1], Vector(3, 4));
+        for i in 0..path.len() - 1 {
+            let step = (result.actions[i], path[i + 1].clone());
+            assert!(search.expand_state(&path[i]).contains(&step));
+        }
+    }
+
+    #[test]
+    fn bidirectional_root_is_goal() {
+        let search = SearchToGoal { goal: Vector(3, 4) };
+        let result = bidirectional_search(Vector(3, 4), Vector(3, 4), &search);
+        assert_eq!(result.path, vec![Vector(3, 4)]);
+        assert_eq!(result.cost, 0);
+    }
+
+    #[test]
+    fn bidirectional_unreachable() {
+        let search = SearchToGoal { goal: Vector(0, 0) };
+        let result = bidirectional_search(Vector(3, 4), Vector(0, 0), &search);
+        assert_eq!(result.outcome, SearchOutcome::Exhausted);
+    }
+
+    #[test]
+    fn bidirectional_respects_max_depth() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let scrambled = scrambled_board(&board, 20);
+        let search = BoardSearchWithGoal {
+            goal: board,
+            max_depth: Some(4),
+        };
+        let result = bidirectional_search(scrambled, board, &search);
+        assert_eq!(result.outcome, SearchOutcome::DepthLimit);
+    }
+
+    #[test]
+    fn bidirectional_on_scrambled_board() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let search = BoardSearchWithGoal {
+            goal: board,
+            max_depth: None,
+        };
+
+        for step in 1..20 {
+            let scrambled = scrambled_board(&board, step);
+
+            let result = bidirectional_search(scrambled, board, &search);
+            let path = result.path;
+            assert!(path[0] == scrambled);
+            assert!(path[path.len() - 1] == board);
+
+            let a_star_path = a_star_search(scrambled, &search).path;
+            assert_eq!(path.len(), a_star_path.len());
+
+            let moves = result.actions;
+            println!("step:{} moves:{:?}", step, moves);
+            assert_eq!(moves, Board::infer_moves_to_empty_position(path));
+            assert_eq!(
+                scrambled
+                    .apply_moves_to_empty_position_get_last(&moves)
+                    .unwrap(),
+                board
+            );
+        }
+    }
+}
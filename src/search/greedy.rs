@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::search::observer::*;
+use crate::search::open_list::*;
+use crate::search::*;
+
+/// Best-first search ordering nodes by the heuristic alone, ties broken by the
+/// `tie_break` of the domain. It does not look at the cost of the path, so the
+/// solution is not the shortest, and with a heuristic full of plateaus it can wander
+/// far from the goal, but with a well informed one it reaches a goal expanding very few
+/// nodes. States are never expanded twice.
+pub fn greedy_best_first_search<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+) -> SearchResult<T, S::Action> {
+    greedy_best_first_search_with(root, search_data, &mut NoObserver, &SearchLimits::none())
+}
+
+pub fn greedy_best_first_search_with<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+    observer: &mut dyn SearchObserver<T>,
+    limits: &SearchLimits,
+) -> SearchResult<T, S::Action> {
+    let started = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut depth_limited = false;
+    let mut best = None;
+    let mut tree = SearchTree::new(search_data);
+    let root = tree.add_root(root);
+
+    let mut not_expanded_nodes: OpenList<T, NodeId> = OpenList::new(search_data.tie_break());
+    let mut expanded_nodes: HashSet<T> = HashSet::new();
+
+    stats.generated(1);
+    observer.generated(tree[root].observed());
+    not_expanded_nodes.push(tree[root].state.clone(), tree[root].h(), 0, root);
+
+    while let Some((state, current)) = not_expanded_nodes.pop() {
+        if search_data.is_goal(&state) {
+            observer.goal_found(tree[current].observed());
+            return SearchResult::from_node(&tree, Some(current), depth_limited, stats, started);
+        }
+
+        if let Some(max) = search_data.max_depth() {
+            if tree[current].level >= max {
+                depth_limited = true;
+                observer.pruned_by_depth(tree[current].observed());
+                continue;
+            }
+        }
+
+        keep_best(&mut best, &tree, current);
+        if limits.exceeded(&stats, expanded_nodes.len(), started) {
+            return SearchResult::budget_exceeded(best, stats, started);
+        }
+
+        let children = tree.expand(current);
+        stats.expanded(tree[current].level);
+        stats.generated(children.len());
+        observer.expanded(tree[current].observed());
+        expanded_nodes.insert(state);
+
+        for child in children {
+            observer.generated(child.observed());
+            // LA h NO CAMBIA CON EL CAMINO: UN ESTADO QUE YA ESTÁ ABIERTO O CERRADO NO SE MEJORA
+            if expanded_nodes.contains(&child.state)
+                || not_expanded_nodes.contains_key(&child.state)
+            {
+                continue;
+            }
+            let state = child.state.clone();
+            let (h, g) = (child.h(), child.g());
+            let id = tree.push(child);
+            not_expanded_nodes.push(state, h, g, id);
+        }
+        stats.frontier(not_expanded_nodes.len());
+        report_progress(observer, &mut stats, started);
+    }
+
+    SearchResult::from_node(&tree, None, depth_limited, stats, started)
+}
+
+/// Breadth first search that only keeps, in every level, the `width` children with the
+/// lowest heuristic, ties broken by the lowest cost. Memory is bounded by `width`
+/// times the depth, but the search is not complete: `Exhausted` means the beam ran out
+/// of nodes, not that there is no goal. A state is never taken into the beam twice.
+pub fn beam_search<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+    width: usize,
+) -> SearchResult<T, S::Action> {
+    beam_search_with(
+        root,
+        search_data,
+        width,
+        &mut NoObserver,
+        &SearchLimits::none(),
+    )
+}
+
+/// The closed set of the limits is every state taken into the beam.
+pub fn beam_search_with<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+    width: usize,
+    observer: &mut dyn SearchObserver<T>,
+    limits: &SearchLimits,
+) -> SearchResult<T, S::Action> {
+    assert!(width > 0, "El haz tiene que tener al menos un nodo");
+    let started = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut depth_limited = false;
+    let mut best = None;
+    let mut tree = SearchTree::new(search_data);
+    let root = tree.add_root(root);
+
+    let mut visited: HashSet<T> = HashSet::new();
+    visited.insert(tree[root].state.clone());
+    stats.generated(1);
+    observer.generated(tree[root].observed());
+    let mut layer = vec![root];
+
+    while !layer.is_empty() {
+        // EL OBJETIVO SE COMPRUEBA AL ENTRAR EN EL HAZ, ANTES DE EXPANDIR EL NIVEL
+        if let Some(&goal) = layer
+            .iter()
+            .find(|id| search_data.is_goal(&tree[**id].state))
+        {
+            observer.goal_found(tree[goal].observed());
+            return SearchResult::from_node(&tree, Some(goal), depth_limited, stats, started);
+        }
+
+        // LOS HIJOS DE TODO EL NIVEL EN ORDEN DE GENERACIÓN, CADA ESTADO CON SU CAMINO MÁS BARATO
+        let mut candidates: Vec<SearchNode<T, S::Action>> = Vec::new();
+        let mut candidate_index: HashMap<T, usize> = HashMap::new();
+        for current in layer {
+            if let Some(max) = search_data.max_depth() {
+                if tree[current].level >= max {
+                    depth_limited = true;
+                    observer.pruned_by_depth(tree[current].observed());
+                    continue;
+                }
+            }
+
+            keep_best(&mut best, &tree, current);
+            if limits.exceeded(&stats, visited.len(), started) {
+                return SearchResult::budget_exceeded(best, stats, started);
+            }
+
+            let children = tree.expand(current);
+            stats.expanded(tree[current].level);
+            stats.generated(children.len());
+            observer.expanded(tree[current].observed());
+            for child in children {
+                observer.generated(child.observed());
+                if visited.contains(&child.state) {
+                    continue;
+                }
+                match candidate_index.get(&child.state) {
+                    Some(&i) => {
+                        if child.g() < candidates[i].g() {
+                            candidates[i] = child;
+                        }
+                    }
+                    None => {
+                        candidate_index.insert(child.state.clone(), candidates.len());
+                        candidates.push(child);
+                    }
+                }
+            }
+            report_progress(observer, &mut stats, started);
+        }
+
+        // SOLO LOS MEJORES PASAN AL ÁRBOL; EL RESTO SE OLVIDA
+        candidates.sort_by_key(|node| (node.h(), node.g()));
+        candidates.truncate(width);
+        layer = candidates
+            .into_iter()
+            .map(|node| {
+                visited.insert(node.state.clone());
+                tree.push(node)
+            })
+            .collect();
+        stats.frontier(layer.len());
+    }
+
+    SearchResult::from_node(&tree, None, depth_limited, stats, started)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crossteaser::crossteaser_search::*;
+    use crate::search::astar::tests::*;
+    use crate::search::astar::*;
+    use crate::search::greedy::*;
+    use crate::search::tests::NoGoal;
+
+    fn assert_valid_path<T: State, S: SearchInfo<T>>(
+        search: &S,
+        result: &SearchResult<T, S::Action>,
+    ) where
+        S::Action: PartialEq,
+    {
+        assert!(result.is_found());
+        assert_eq!(result.actions.len(), result.path.len() - 1);
+        for i in 0..result.actions.len() {
+            let step = (result.actions[i].clone(), result.path[i + 1].clone());
+            assert!(search.expand_state(&result.path[i]).contains(&step));
+        }
+        assert!(search.is_goal(result.path.last().unwrap()));
+    }
+
+    #[test]
+    fn greedy_to_goal() {
+        let search = SearchToGoal { goal: Vector(3, 4) };
+        let result = greedy_best_first_search(Vector(0, 0), &search);
+        assert_valid_path(&search, &result);
+        assert_eq!(result.cost, 7);
+        // LA HEURÍSTICA ES EXACTA: SOLO SE EXPANDE EL CAMINO
+        assert_eq!(result.stats.expanded, 7);
+    }
+
+    #[test]
+    fn greedy_ignores_cost() {
+        let search = SearchInTerrain {
+            terrain: vec![vec![1, 9, 1], vec![1, 1, 1], vec![1, 1, 1]],
+            goal: Vector(2, 0),
+        };
+        let result = greedy_best_first_search(Vector(0, 0), &search);
+        assert_valid_path(&search, &result);
+        // LA VORAZ CRUZA LA CASILLA CARA; A* DA LA VUELTA
+        assert_eq!(result.cost, 10);
+        assert_eq!(a_star_search(Vector(0, 0), &search).cost, 4);
+    }
+
+    #[test]
+    fn greedy_and_beam_without_goal() {
+        let search = NoGoal {};
+        let greedy = greedy_best_first_search(vec![], &search);
+        assert_eq!(greedy.outcome, SearchOutcome::Exhausted);
+        assert_eq!(greedy.stats.generated, 1 + 4 + 16 + 64 + 256);
+
+        let beam = beam_search(vec![], &search, 2);
+        assert_eq!(beam.outcome, SearchOutcome::Exhausted);
+        assert_eq!(beam.stats.expanded, 1 + 2 + 2 + 2 + 2);
+    }
+
+    #[test]
+    fn beam_to_goal() {
+        let search = SearchToGoal { goal: Vector(3, 4) };
+        for width in 1..4 {
+            let result = beam_search(Vector(0, 0), &search, width);
+            assert_valid_path(&search, &result);
+            assert_eq!(result.cost, 7);
+            assert!(result.stats.peak_frontier <= width);
+        }
+    }
+
+    #[test]
+    fn beam_respects_max_depth() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let search = BoardSearchWithGoal {
+            goal: board,
+            max_depth: Some(4),
+        };
+        let result = beam_search(scrambled_board(&board, 20), &search, 10);
+        assert_eq!(result.outcome, SearchOutcome::DepthLimit);
+        assert_eq!(result.stats.max_depth_reached, 3);
+    }
+
+    #[test]
+    fn greedy_stops_at_limits() {
+        let search = SearchToGoal { goal: Vector(0, 0) };
+        let limits = SearchLimits {
+            max_expanded: Some(20),
+            ..SearchLimits::none()
+        };
+        let result = greedy_best_first_search_with(Vector(1, 1), &search, &mut NoObserver, &limits);
+        assert_eq!(result.outcome, SearchOutcome::BudgetExceeded);
+        assert_eq!(result.stats.expanded, 20);
+    }
+
+    #[test]
+    fn greedy_and_beam_on_scrambled_boards() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let search = HalfOfDifferences { goal: board };
+
+        for step in (10..=40).step_by(5) {
+            let scrambled = scrambled_board(&board, step);
+            let optimal = a_star_search(scrambled, &search);
+
+            // LA MITAD DE LAS DIFERENCIAS TIENE MESETAS MUY GRANDES: LA VORAZ SOLO CERCA DEL OBJETIVO
+            let mut results = vec![("beam", beam_search(scrambled, &search, 100))];
+            if optimal.cost <= 10 {
+                results.push(("greedy", greedy_best_first_search(scrambled, &search)));
+            }
+            for (name, result) in results.iter() {
+                println!(
+                    "step:{} a*:{} {} {}:{} {}",
+                    step,
+                    optimal.cost,
+                    optimal.stats.expanded,
+                    name,
+                    result.cost,
+                    result.stats.expanded
+                );
+                assert_valid_path(&search, result);
+                assert!(result.cost >= optimal.cost);
+                assert_eq!(
+                    scrambled
+                        .apply_moves_to_empty_position_get_last(&result.actions)
+                        .unwrap(),
+                    board
+                );
+            }
+        }
+
+        // CON LA DISTANCIA RODANDO LA VORAZ LLEGA TAMBIÉN DESDE LEJOS, AUNQUE DANDO MUCHAS VUELTAS
+        let rolling = BoardSearchWithGoal {
+            goal: board,
+            max_depth: None,
+        };
+        let scrambled = scrambled_board(&board, 40);
+        let optimal = a_star_search(scrambled, &rolling);
+        let result = greedy_best_first_search(scrambled, &rolling);
+        println!(
+            "step:40 a*:{} {} greedy:{} {}",
+            optimal.cost, optimal.stats.expanded, result.cost, result.stats.expanded
+        );
+        assert_valid_path(&rolling, &result);
+        assert!(result.cost >= optimal.cost);
+        assert_eq!(
+            scrambled
+                .apply_moves_to_empty_position_get_last(&result.actions)
+                .unwrap(),
+            board
+        );
+    }
+}
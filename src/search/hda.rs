@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+use crate::search::open_list::*;
+use crate::search::*;
+
+const INFINITE: u64 = u64::MAX;
+
+// UN NODO QUE SE MANDA AL HILO DUEÑO DE SU ESTADO
+struct Message<T, A> {
+    state: T,
+    g: u64,
+    level: u64,
+    parent: Option<(T, A)>,
+}
+
+// LOS HILOS SIN NADA QUE HACER ESPERAN EN SU BUZÓN: None LES AVISA DE QUE SE HA ACABADO
+type Mail<T, A> = Option<Message<T, A>>;
+
+struct Seen<T, A> {
+    g: u64,
+    // SE CALCULA UNA VEZ, AL RECIBIR EL ESTADO POR PRIMERA VEZ
+    h: u64,
+    parent: Option<(T, A)>,
+}
+
+// LO QUE COMPARTEN TODOS LOS HILOS
+struct Shared {
+    incumbent: AtomicU64,
+    // MENSAJES ENVIADOS QUE TODAVÍA NO SE HAN METIDO EN LA ABIERTA DE SU DUEÑO, Y TODOS LOS
+    // ENVIADOS DESDE EL PRINCIPIO
+    in_flight: AtomicUsize,
+    sent: AtomicUsize,
+    idle: Vec<AtomicBool>,
+    done: AtomicBool,
+    stopped: AtomicBool,
+    depth_limited: AtomicBool,
+    expanded: AtomicUsize,
+    closed: AtomicUsize,
+}
+
+impl Shared {
+    // TODOS LOS HILOS SIN NADA MEJOR QUE LA SOLUCIÓN Y NINGÚN MENSAJE POR EL CAMINO. SI SE
+    // ENVÍA ALGO MIENTRAS SE MIRA, `sent` CAMBIA Y SE VUELVE A INTENTAR MÁS TARDE
+    fn quiescent(&self) -> bool {
+        let sent = self.sent.load(Ordering::SeqCst);
+        self.in_flight.load(Ordering::SeqCst) == 0
+            && self.idle.iter().all(|idle| idle.load(Ordering::SeqCst))
+            && self.in_flight.load(Ordering::SeqCst) == 0
+            && self.sent.load(Ordering::SeqCst) == sent
+    }
+}
+
+struct Worker<'a, T: State, S: SearchInfo<T> + ?Sized> {
+    id: usize,
+    search_data: &'a S,
+    open: OpenList<T, u64>,
+    seen: HashMap<T, Seen<T, S::Action>>,
+    stats: SearchStats,
+    goal: Option<T>,
+    // EL NODO DE MENOR h, POR SI SE ACABA EL PRESUPUESTO SIN SOLUCIÓN
+    best: Option<(u64, u64, T)>,
+}
+
+fn owner<T: State>(state: &T, threads: usize) -> usize {
+    (simple_hash(state) % threads as u64) as usize
+}
+
+impl<'a, T: State, S: SearchInfo<T> + ?Sized> Worker<'a, T, S> {
+    // SOLO SE QUEDA EL CAMINO MÁS BARATO A CADA ESTADO; SI YA SE HABÍA EXPANDIDO SE REABRE
+    fn receive(&mut self, message: Message<T, S::Action>) {
+        let h = match self.seen.get(&message.state) {
+            Some(seen) if seen.g <= message.g => return,
+            Some(seen) => seen.h,
+            None => self.search_data.heuristic(&message.state),
+        };
+        let f = message.g + h;
+        self.open
+            .push(message.state.clone(), f, message.g, message.level);
+        self.seen.insert(
+            message.state,
+            Seen {
+                g: message.g,
+                h,
+                parent: message.parent,
+            },
+        );
+    }
+
+    // SE AVISA A LOS DEMÁS, QUE PUEDEN ESTAR ESPERANDO EN SU BUZÓN
+    fn finish(&self, shared: &Shared, outboxes: &[Sender<Mail<T, S::Action>>]) {
+        shared.done.store(true, Ordering::SeqCst);
+        for (id, outbox) in outboxes.iter().enumerate() {
+            if id != self.id {
+                outbox.send(None).ok();
+            }
+        }
+    }
+
+    fn run(
+        &mut self,
+        inbox: Receiver<Mail<T, S::Action>>,
+        outboxes: Vec<Sender<Mail<T, S::Action>>>,
+        shared: &Shared,
+        limits: &SearchLimits,
+        started: Instant,
+    ) {
+        let threads = outboxes.len();
+        while !shared.done.load(Ordering::SeqCst) {
+            while let Ok(Some(message)) = inbox.try_recv() {
+                shared.idle[self.id].store(false, Ordering::SeqCst);
+                self.receive(message);
+                shared.in_flight.fetch_sub(1, Ordering::SeqCst);
+            }
+
+            // LA SOLUCIÓN SOLO BAJA: LO QUE NO LA MEJORA YA NO LA VA A MEJORAR NUNCA
+            let incumbent = shared.incumbent.load(Ordering::SeqCst);
+            let best_f = self
+                .open
+                .peek()
+                .map(|(state, _)| self.open.f(state).unwrap());
+            let (state, level) = match best_f {
+                Some(f) if f < incumbent => self.open.pop().unwrap(),
+                _ => {
+                    // EL ÚLTIMO EN QUEDARSE SIN TRABAJO ES EL QUE VE A TODOS PARADOS
+                    shared.idle[self.id].store(true, Ordering::SeqCst);
+                    if shared.quiescent() {
+                        self.finish(shared, &outboxes);
+                        break;
+                    }
+                    match inbox.recv() {
+                        Ok(Some(message)) => {
+                            shared.idle[self.id].store(false, Ordering::SeqCst);
+                            self.receive(message);
+                            shared.in_flight.fetch_sub(1, Ordering::SeqCst);
+                        }
+                        _ => break,
+                    }
+                    continue;
+                }
+            };
+            let Seen { g, h, .. } = self.seen[&state];
+
+            if self.search_data.is_goal(&state) {
+                if g < shared.incumbent.fetch_min(g, Ordering::SeqCst) {
+                    self.goal = Some(state);
+                }
+                continue;
+            }
+
+            if let Some(max) = self.search_data.max_depth() {
+                if level >= max {
+                    shared.depth_limited.store(true, Ordering::SeqCst);
+                    continue;
+                }
+            }
+
+            if self
+                .best
+                .as_ref()
+                .is_none_or(|(bh, bg, _)| (h, g) < (*bh, *bg))
+            {
+                self.best = Some((h, g, state.clone()));
+            }
+            let global = SearchStats {
+                expanded: shared.expanded.load(Ordering::SeqCst),
+                ..SearchStats::default()
+            };
+            if limits.exceeded(&global, shared.closed.load(Ordering::SeqCst), started) {
+                shared.stopped.store(true, Ordering::SeqCst);
+                self.finish(shared, &outboxes);
+                break;
+            }
+
+            let children = self.search_data.expand_state(&state);
+            self.stats.expanded(level);
+            self.stats.generated(children.len());
+            shared.expanded.fetch_add(1, Ordering::SeqCst);
+            shared.closed.fetch_add(1, Ordering::SeqCst);
+            for (action, child) in children {
+                let child_g = g + self.search_data.step_cost(&state, &child);
+                if child_g >= shared.incumbent.load(Ordering::SeqCst) {
+                    continue;
+                }
+                let message = Message {
+                    parent: Some((state.clone(), action)),
+                    g: child_g,
+                    level: level + 1,
+                    state: child,
+                };
+                let to = owner(&message.state, threads);
+                if to == self.id {
+                    self.receive(message);
+                } else {
+                    shared.in_flight.fetch_add(1, Ordering::SeqCst);
+                    shared.sent.fetch_add(1, Ordering::SeqCst);
+                    outboxes[to].send(Some(message)).ok();
+                }
+            }
+            self.stats.frontier(self.open.len());
+        }
+    }
+}
+
+// LOS ESTADOS Y ACCIONES DESDE LA RAÍZ HASTA `state`, BUSCANDO CADA PADRE EN SU DUEÑO
+fn root_path<T: State, S: SearchInfo<T> + ?Sized>(
+    workers: &[Worker<T, S>],
+    state: &T,
+) -> (Vec<T>, Vec<S::Action>) {
+    let mut states = vec![state.clone()];
+    let mut actions = Vec::new();
+    let mut current = state.clone();
+    while let Some((parent, action)) =
+        &workers[owner(&current, workers.len())].seen[&current].parent
+    {
+        states.push(parent.clone());
+        actions.push(action.clone());
+        current = parent.clone();
+    }
+    states.reverse();
+    actions.reverse();
+    (states, actions)
+}
+
+/// Hash distributed A* (HDA*): every state belongs to one of `threads` workers, chosen
+/// by its hash, and only its owner keeps it in its open and closed lists. The
+/// successors of a state are sent to their owners through channels. The first
+/// solution only bounds the search: the workers go on until none of them has a node
+/// with f lower than the best solution and no node is on its way between them, so
+/// with an admissible heuristic the solution is optimal. The domain and the states have
+/// to be shared between threads. `peak_frontier` is the sum of the peaks of every
+/// worker.
+pub fn hda_star_search<T, S>(root: T, search_data: &S, threads: usize) -> SearchResult<T, S::Action>
+where
+    T: State + Send + Sync,
+    S: SearchInfo<T> + Sync + ?Sized,
+    S::Action: Send + Sync,
+{
+    hda_star_search_with(root, search_data, threads, &SearchLimits::none())
+}
+
+/// There is no observer, because the workers run at the same time. The limits are
+/// checked by every worker against the totals of all of them.
+pub fn hda_star_search_with<T, S>(
+    root: T,
+    search_data: &S,
+    threads: usize,
+    limits: &SearchLimits,
+) -> SearchResult<T, S::Action>
+where
+    T: State + Send + Sync,
+    S: SearchInfo<T> + Sync + ?Sized,
+    S::Action: Send + Sync,
+{
+    assert!(threads > 0, "Hace falta al menos un hilo");
+    let started = Instant::now();
+    let shared = Shared {
+        incumbent: AtomicU64::new(INFINITE),
+        in_flight: AtomicUsize::new(1),
+        sent: AtomicUsize::new(1),
+        idle: (0..threads).map(|_| AtomicBool::new(false)).collect(),
+        done: AtomicBool::new(false),
+        stopped: AtomicBool::new(false),
+        depth_limited: AtomicBool::new(false),
+        expanded: AtomicUsize::new(0),
+        closed: AtomicUsize::new(0),
+    };
+    let (outboxes, inboxes): (Vec<_>, Vec<_>) = (0..threads).map(|_| channel()).unzip();
+    outboxes[owner(&root, threads)]
+        .send(Some(Message {
+            state: root,
+            g: 0,
+            level: 0,
+            parent: None,
+        }))
+        .ok();
+
+    let workers: Vec<Worker<T, S>> = thread::scope(|scope| {
+        let handles: Vec<_> = inboxes
+            .into_iter()
+            .enumerate()
+            .map(|(id, inbox)| {
+                let outboxes = outboxes.clone();
+                let shared = &shared;
+                scope.spawn(move || {
+                    let mut worker = Worker {
+                        id,
+                        search_data,
+                        open: OpenList::new(search_data.tie_break()),
+                        seen: HashMap::new(),
+                        stats: SearchStats::default(),
+                        goal: None,
+                        best: None,
+                    };
+                    worker.run(inbox, outboxes, shared, limits, started);
+                    worker
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let mut stats = SearchStats {
+        generated: 1,
+        ..SearchStats::default()
+    };
+    for worker in workers.iter() {
+        stats.expanded += worker.stats.expanded;
+        stats.generated += worker.stats.generated;
+        stats.peak_frontier += worker.stats.peak_frontier;
+        stats.max_depth_reached = stats.max_depth_reached.max(worker.stats.max_depth_reached);
+    }
+
+    // LA MEJOR SOLUCIÓN ES LA DE MENOR COSTE ENTRE LAS QUE HA ENCONTRADO CADA HILO
+    let goal = workers
+        .iter()
+        .filter_map(|w| w.goal.as_ref().map(|goal| (w.seen[goal].g, goal)))
+        .min_by_key(|(g, _)| *g);
+    let path = goal.map(|(_, goal)| {
+        let (path, actions) = root_path(&workers, goal);
+        let cost = path
+            .windows(2)
+            .map(|w| search_data.step_cost(&w[0], &w[1]))
+            .sum();
+        (path, actions, cost)
+    });
+
+    if shared.stopped.load(Ordering::SeqCst) {
+        let best = match path {
+            Some(path) => Some(path),
+            None => workers
+                .iter()
+                .filter_map(|w| w.best.as_ref())
+                .min_by_key(|(h, g, _)| (*h, *g))
+                .map(|(_, g, state)| {
+                    let (path, actions) = root_path(&workers, state);
+                    (path, actions, *g)
+                }),
+        };
+        let best = best.map(|(path, actions, g)| BestNode {
+            h: 0,
+            g,
+            path,
+            actions,
+        });
+        return SearchResult::budget_exceeded(best, stats, started);
+    }
+    let depth_limited = shared.depth_limited.load(Ordering::SeqCst);
+    SearchResult::from_path(path, depth_limited, stats, started)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crossteaser::crossteaser_search::*;
+    use crate::search::astar::tests::*;
+    use crate::search::astar::*;
+    use crate::search::hda::*;
+    use crate::search::tests::NoGoal;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn boards_can_be_shared_between_threads() {
+        assert_send_sync::<Board>();
+        assert_send_sync::<HalfOfDifferences>();
+        assert_send_sync::<SearchNode<Board, Direction>>();
+    }
+
+    #[test]
+    fn hda_in_terrain() {
+        let search = SearchInTerrain {
+            terrain: vec![
+                vec![1, 1, 1, 1, 1],
+                vec![1, 9, 9, 9, 1],
+                vec![1, 9, 1, 9, 1],
+                vec![1, 9, 1, 9, 1],
+                vec![1, 1, 1, 1, 1],
+            ],
+            goal: Vector(2, 2),
+        };
+        for threads in 1..=4 {
+            let result = hda_star_search(Vector(0, 0), &search, threads);
+            assert_eq!(result.cost, 8);
+            assert_eq!(result.actions.len(), result.path.len() - 1);
+            assert_eq!(result.path.last(), Some(&Vector(2, 2)));
+        }
+    }
+
+    #[test]
+    fn hda_without_goal() {
+        let result = hda_star_search(vec![], &NoGoal {}, 3);
+        assert_eq!(result.outcome, SearchOutcome::Exhausted);
+        assert_eq!(result.stats.expanded, 1 + 4 + 16 + 64 + 256);
+    }
+
+    #[test]
+    fn hda_stops_at_limits() {
+        let search = SearchToGoal { goal: Vector(0, 0) };
+        let limits = SearchLimits {
+            max_expanded: Some(50),
+            ..SearchLimits::none()
+        };
+        let result = hda_star_search_with(Vector(1, 1), &search, 4, &limits);
+        assert_eq!(result.outcome, SearchOutcome::BudgetExceeded);
+        assert!(result.stats.expanded >= 50);
+    }
+
+    #[test]
+    fn hda_optimal_on_scrambled_boards() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let search = HalfOfDifferences { goal: board };
+
+        for step in (10..=40).step_by(10) {
+            let scrambled = scrambled_board(&board, step);
+            let optimal = a_star_search(scrambled, &search);
+            for threads in [1, 2, 4].iter() {
+                let result = hda_star_search(scrambled, &search, *threads);
+                println!(
+                    "step:{} threads:{} a*:{} {} hda:{} {} {:?}",
+                    step,
+                    threads,
+                    optimal.cost,
+                    optimal.stats.expanded,
+                    result.cost,
+                    result.stats.expanded,
+                    result.stats.elapsed
+                );
+                assert_eq!(result.cost, optimal.cost);
+                assert_eq!(
+                    scrambled
+                        .apply_moves_to_empty_position_get_last(&result.actions)
+                        .unwrap(),
+                    board
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::HashSet;
+
+use crate::search::uniform_cost::*;
+use crate::search::*;
+
+/// Buckets of the histogram of h/h*: one per tenth, and the last one for 1 or more.
+pub const HISTOGRAM_BUCKETS: usize = 11;
+
+/// A state whose heuristic exceeds its true cost to the goal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdmissibilityViolation<T> {
+    pub state: T,
+    pub heuristic: u64,
+    pub true_cost: u64,
+}
+
+/// A move along which the heuristic drops more than the cost of the move.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsistencyViolation<T> {
+    pub from: T,
+    pub to: T,
+    pub step_cost: u64,
+    pub from_heuristic: u64,
+    pub to_heuristic: u64,
+}
+
+/// What `check_heuristic` found.
+#[derive(Debug, Clone)]
+pub struct HeuristicReport<T> {
+    /// States with a known true cost: the samples and the states of their optimal paths.
+    pub checked_states: usize,
+    /// Samples without a goal, or whose search exceeded the limits.
+    pub unsolved_samples: usize,
+    /// Moves checked for consistency: the ones out of every checked state.
+    pub checked_moves: usize,
+    pub admissibility_violations: Vec<AdmissibilityViolation<T>>,
+    pub consistency_violations: Vec<ConsistencyViolation<T>>,
+    /// Mean of h/h* over the checked states that are not goals, 0 if there are none.
+    pub average_ratio: f64,
+    /// Checked states that are not goals by h/h*, see `HISTOGRAM_BUCKETS`.
+    pub histogram: [usize; HISTOGRAM_BUCKETS],
+}
+
+impl<T> HeuristicReport<T> {
+    pub fn is_admissible(&self) -> bool {
+        self.admissibility_violations.is_empty()
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.consistency_violations.is_empty()
+    }
+}
+
+impl<T> Display for HeuristicReport<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f,
+            "states:{} unsolved:{} moves:{} not admissible:{} not consistent:{} h/h*:{:.3} histogram:{:?}",
+            self.checked_states,
+            self.unsolved_samples,
+            self.checked_moves,
+            self.admissibility_violations.len(),
+            self.consistency_violations.len(),
+            self.average_ratio,
+            self.histogram
+        )
+    }
+}
+
+/// Compares the heuristic of `search_data` with the true costs, found with a uniform
+/// cost search from every sample. Every state of the optimal path of a sample has a
+/// known cost too, so all of them are checked, along with the moves out of them.
+pub fn check_heuristic<T: State, S: SearchInfo<T> + ?Sized>(
+    search_data: &S,
+    samples: &[T],
+) -> HeuristicReport<T> {
+    check_heuristic_with(search_data, samples, &SearchLimits::none())
+}
+
+/// The limits apply to the search of each sample; if it stops, the sample counts as
+/// unsolved and only the moves out of it are checked.
+pub fn check_heuristic_with<T: State, S: SearchInfo<T> + ?Sized>(
+    search_data: &S,
+    samples: &[T],
+    limits: &SearchLimits,
+) -> HeuristicReport<T> {
+    let mut report = HeuristicReport {
+        checked_states: 0,
+        unsolved_samples: 0,
+        checked_moves: 0,
+        admissibility_violations: Vec::new(),
+        consistency_violations: Vec::new(),
+        average_ratio: 0.0,
+        histogram: [0; HISTOGRAM_BUCKETS],
+    };
+    let mut checked: HashSet<T> = HashSet::new();
+    let mut ratios = 0.0;
+    let mut not_goals = 0;
+
+    for sample in samples.iter() {
+        let result = uniform_cost_search_with(sample.clone(), search_data, &mut NoObserver, limits);
+        // CADA ESTADO CON LO QUE LE FALTA HASTA EL OBJETIVO, SI SE SABE
+        let mut to_check: Vec<(T, Option<u64>)> = Vec::new();
+        if result.is_found() {
+            let mut remaining = result.cost;
+            for (i, state) in result.path.iter().enumerate() {
+                to_check.push((state.clone(), Some(remaining)));
+                if let Some(next) = result.path.get(i + 1) {
+                    remaining -= search_data.step_cost(state, next);
+                }
+            }
+        } else {
+            report.unsolved_samples += 1;
+            to_check.push((sample.clone(), None));
+        }
+
+        for (state, true_cost) in to_check {
+            if !checked.insert(state.clone()) {
+                continue;
+            }
+            let heuristic = search_data.heuristic(&state);
+
+            if let Some(true_cost) = true_cost {
+                report.checked_states += 1;
+                if heuristic > true_cost {
+                    report
+                        .admissibility_violations
+                        .push(AdmissibilityViolation {
+                            state: state.clone(),
+                            heuristic,
+                            true_cost,
+                        });
+                }
+                if true_cost > 0 {
+                    let ratio = heuristic as f64 / true_cost as f64;
+                    ratios += ratio;
+                    not_goals += 1;
+                    let bucket = (ratio * 10.0).floor() as usize;
+                    report.histogram[bucket.min(HISTOGRAM_BUCKETS - 1)] += 1;
+                }
+            }
+
+            for (_, to) in search_data.expand_state(&state) {
+                report.checked_moves += 1;
+                let step_cost = search_data.step_cost(&state, &to);
+                let to_heuristic = search_data.heuristic(&to);
+                if heuristic > step_cost + to_heuristic {
+                    report.consistency_violations.push(ConsistencyViolation {
+                        from: state.clone(),
+                        to,
+                        step_cost,
+                        from_heuristic: heuristic,
+                        to_heuristic,
+                    });
+                }
+            }
+        }
+    }
+
+    if not_goals > 0 {
+        report.average_ratio = ratios / not_goals as f64;
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search::astar::tests::*;
+    use crate::search::heuristic_check::*;
+    use crate::search::tests::NoGoal;
+
+    #[test]
+    fn euclidean_heuristic_is_admissible_and_consistent() {
+        let search = SearchToGoal { goal: Vector(3, 4) };
+        let samples = vec![Vector(0, 0), Vector(1, 2), Vector(3, 0), Vector(3, 4)];
+        let report = check_heuristic(&search, &samples);
+        println!("{}", report);
+        assert!(report.is_admissible());
+        assert!(report.is_consistent());
+        // LOS CAMINOS SE SOLAPAN, PERO CADA ESTADO SE COMPRUEBA UNA SOLA VEZ
+        assert!(report.checked_states >= 8);
+        assert_eq!(report.unsolved_samples, 0);
+        assert!(report.average_ratio > 0.5 && report.average_ratio <= 1.0);
+        assert_eq!(
+            report.histogram.iter().sum::<usize>(),
+            report.checked_states - 1
+        );
+    }
+
+    #[test]
+    fn manhattan_in_terrain_is_admissible() {
+        let search = SearchInTerrain {
+            terrain: vec![vec![1, 9, 1], vec![1, 9, 1], vec![1, 1, 1]],
+            goal: Vector(2, 0),
+        };
+        let report = check_heuristic(&search, &[Vector(0, 0), Vector(1, 1)]);
+        println!("{}", report);
+        assert!(report.is_admissible());
+        // EL CAMINO DA LA VUELTA Y CUESTA MUCHO MÁS QUE LA DISTANCIA
+        assert!(report.average_ratio < 1.0);
+    }
+
+    #[test]
+    fn half_of_differences_is_admissible() {
+        use crate::crossteaser::crossteaser_search::tests::scrambled_samples;
+        use crate::crossteaser::crossteaser_search::*;
+
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let search = HalfOfDifferences { goal: board };
+        let samples = scrambled_samples(&board, 10, 25);
+        let report = check_heuristic(&search, &samples);
+        println!("{}", report);
+        assert!(report.is_admissible());
+        assert!(report.is_consistent());
+    }
+
+    #[test]
+    fn finds_inconsistent_heuristic() {
+        let search = inconsistent_graph();
+        let report = check_heuristic(&search, &['S', 'A', 'B']);
+        println!("{}", report);
+        assert!(report.is_admissible());
+        assert!(!report.is_consistent());
+        assert_eq!(
+            report.consistency_violations,
+            vec![ConsistencyViolation {
+                from: 'A',
+                to: 'C',
+                step_cost: 1,
+                from_heuristic: 5,
+                to_heuristic: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn finds_overestimating_heuristic() {
+        let mut search = inconsistent_graph();
+        search.heuristic = vec![('A', 7), ('C', 5)];
+        let report = check_heuristic(&search, &['S']);
+        println!("{}", report);
+        // S VA POR A Y C: A ESTÁ A 6 Y C A 5
+        assert_eq!(
+            report.admissibility_violations,
+            vec![AdmissibilityViolation {
+                state: 'A',
+                heuristic: 7,
+                true_cost: 6,
+            }]
+        );
+        assert_eq!(report.histogram[HISTOGRAM_BUCKETS - 1], 2);
+    }
+
+    #[test]
+    fn samples_without_goal() {
+        let limits = SearchLimits {
+            max_expanded: Some(10),
+            ..SearchLimits::none()
+        };
+        let report = check_heuristic_with(&NoGoal {}, &[vec![]], &limits);
+        assert_eq!(report.unsolved_samples, 1);
+        assert_eq!(report.checked_states, 0);
+        assert_eq!(report.checked_moves, 4);
+        assert_eq!(report.average_ratio, 0.0);
+    }
+}
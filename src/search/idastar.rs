@@ -0,0 +1,197 @@
+use crate::search::observer::*;
+use crate::search::tree::*;
+use crate::search::*;
+
+enum IdaStep {
+    Found(NodeId),
+    Exceeded(Option<u64>),
+    /// The limits of the search were exceeded.
+    Stopped,
+}
+
+struct Ida<'o, 'a, T: State, S: SearchInfo<T> + ?Sized> {
+    tree: SearchTree<'a, T, S>,
+    stats: SearchStats,
+    depth_limited: bool,
+    best: Option<BestNode<T, S::Action>>,
+    observer: &'o mut dyn SearchObserver<T>,
+    limits: &'o SearchLimits,
+    started: Instant,
+}
+
+fn bounded_search<T: State, S: SearchInfo<T> + ?Sized>(
+    current: NodeId,
+    bound: u64,
+    ida: &mut Ida<T, S>,
+) -> IdaStep {
+    let search_data = ida.tree.search();
+    let level = ida.tree[current].level;
+    let f = ida.tree[current].f();
+
+    if f > bound {
+        return IdaStep::Exceeded(Some(f));
+    }
+
+    if search_data.is_goal(&ida.tree[current].state) {
+        ida.observer.goal_found(ida.tree[current].observed());
+        return IdaStep::Found(current);
+    }
+
+    if let Some(max) = search_data.max_depth() {
+        if level >= max {
+            ida.depth_limited = true;
+            ida.observer.pruned_by_depth(ida.tree[current].observed());
+            return IdaStep::Exceeded(None);
+        }
+    }
+
+    keep_best(&mut ida.best, &ida.tree, current);
+    if ida
+        .limits
+        .exceeded(&ida.stats, level as usize + 1, ida.started)
+    {
+        return IdaStep::Stopped;
+    }
+
+    let children = ida.tree.expand_and_push(current);
+    let explored = ida.tree.len();
+    ida.stats.expanded(level);
+    ida.stats.generated(children.len());
+    ida.stats.frontier(level as usize + 1);
+    ida.observer.expanded(ida.tree[current].observed());
+    for child in children.iter() {
+        ida.observer.generated(ida.tree[*child].observed());
+    }
+    report_progress(ida.observer, &mut ida.stats, ida.started);
+    let mut next_bound: Option<u64> = None;
+    for child in children {
+        // LOS CICLOS DENTRO DEL CAMINO ACTUAL NUNCA LLEVAN A UNA SOLUCIÓN MEJOR
+        if ida.tree.in_root_path(current, &ida.tree[child].state) {
+            continue;
+        }
+        match bounded_search(child, bound, ida) {
+            IdaStep::Found(found) => return IdaStep::Found(found),
+            IdaStep::Stopped => return IdaStep::Stopped,
+            IdaStep::Exceeded(Some(f)) => {
+                next_bound = Some(next_bound.map_or(f, |b| b.min(f)));
+            }
+            IdaStep::Exceeded(None) => {}
+        }
+        ida.tree.truncate(explored);
+    }
+    IdaStep::Exceeded(next_bound)
+}
+
+/// Iterative deepening A*: depth first searches bounded by f = cost + heuristic,
+/// raising the bound to the smallest f that exceeded it. Only the current path is
+/// kept in memory. The stats add up every iteration.
+pub fn ida_star_search<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+) -> SearchResult<T, S::Action> {
+    ida_star_search_with(root, search_data, &mut NoObserver, &SearchLimits::none())
+}
+
+pub fn ida_star_search_with<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+    observer: &mut dyn SearchObserver<T>,
+    limits: &SearchLimits,
+) -> SearchResult<T, S::Action> {
+    let mut ida = Ida {
+        tree: SearchTree::new(search_data),
+        stats: SearchStats::default(),
+        depth_limited: false,
+        best: None,
+        observer,
+        limits,
+        started: Instant::now(),
+    };
+    let root = ida.tree.add_root(root);
+    ida.stats.generated(1);
+    ida.observer.generated(ida.tree[root].observed());
+    let mut bound = ida.tree[root].f();
+
+    let found = loop {
+        match bounded_search(root, bound, &mut ida) {
+            IdaStep::Found(found) => break Some(found),
+            IdaStep::Stopped => {
+                return SearchResult::budget_exceeded(ida.best, ida.stats, ida.started)
+            }
+            IdaStep::Exceeded(Some(next_bound)) => {
+                // CADA ITERACIÓN EMPIEZA CON UN ÁRBOL QUE SOLO TIENE LA RAÍZ
+                ida.tree.truncate(root + 1);
+                bound = next_bound
+            }
+            IdaStep::Exceeded(None) => break None,
+        }
+    };
+    SearchResult::from_node(&ida.tree, found, ida.depth_limited, ida.stats, ida.started)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crossteaser::crossteaser_search::*;
+    use crate::search::astar::tests::*;
+    use crate::search::astar::*;
+    use crate::search::idastar::*;
+
+    #[test]
+    fn ida_star_test() {
+        let search = SearchToGoal { goal: Vector(3, 4) };
+        let result = ida_star_search(Vector(0, 0), &search);
+        assert!(result.is_found());
+        assert!(*result.goal().unwrap() == Vector(3, 4));
+
+        let a_star_result = a_star_search(Vector(0, 0), &search);
+
+        assert_eq!(result.path[0], Vector(0, 0));
+        assert_eq!(result.path.len(), a_star_result.path.len());
+        assert_eq!(result.cost, a_star_result.cost);
+    }
+
+    #[test]
+    fn ida_star_respects_max_depth() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let search = BoardSearchWithGoal {
+            goal: Board::from_piece(&piece_set, 0),
+            max_depth: Some(3),
+        };
+        let board = Board::from_piece(&piece_set, 1);
+        let result = ida_star_search(board, &search);
+        assert_eq!(result.outcome, SearchOutcome::DepthLimit);
+        assert_eq!(result.stats.max_depth_reached, 2);
+    }
+
+    #[test]
+    fn ida_star_on_scrambled_board() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let search = BoardSearchWithGoal {
+            goal: board,
+            max_depth: None,
+        };
+
+        for step in 1..20 {
+            let scrambled = scrambled_board(&board, step);
+
+            let result = ida_star_search(scrambled, &search);
+            let path = result.path;
+            assert!(path[0] == scrambled);
+            assert!(path[path.len() - 1] == board);
+
+            let a_star_path = a_star_search(scrambled, &search).path;
+            println!("step:{} ida*:{} a*:{}", step, path.len(), a_star_path.len());
+            assert_eq!(path.len(), a_star_path.len());
+
+            let moves = result.actions;
+            assert_eq!(moves, Board::infer_moves_to_empty_position(path));
+            assert_eq!(
+                scrambled
+                    .apply_moves_to_empty_position_get_last(&moves)
+                    .unwrap(),
+                board
+            );
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use crate::search::observer::*;
+use crate::search::*;
+
+/// Depth first searches with a depth limit that starts at 0 and grows by one until a
+/// goal is found. The first solution has the fewest moves, while only the current path
+/// is kept in memory. `step_cost` does not change the order of the search, only the
+/// cost of the result. The stats add up every iteration.
+pub fn iterative_deepening_search<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+) -> SearchResult<T, S::Action> {
+    iterative_deepening_search_with(root, search_data, &mut NoObserver, &SearchLimits::none())
+}
+
+/// The observer is told about the nodes pruned by the limit of every iteration.
+pub fn iterative_deepening_search_with<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+    observer: &mut dyn SearchObserver<T>,
+    limits: &SearchLimits,
+) -> SearchResult<T, S::Action> {
+    let mut dfs = DepthFirst::new(search_data, observer, limits);
+    let root = dfs.tree.add_root(root);
+    dfs.stats.generated(1);
+    dfs.observer.generated(dfs.tree[root].observed());
+
+    let mut limit = 0;
+    let (found, depth_limited) = loop {
+        match dfs.search(root, Some(limit)) {
+            DepthFirstEnd::Found(found) => break (Some(found), false),
+            DepthFirstEnd::Stopped => {
+                return SearchResult::budget_exceeded(dfs.best, dfs.stats, dfs.started)
+            }
+            // NINGÚN NODO LLEGÓ AL LÍMITE: NO HAY NADA MÁS PROFUNDO
+            DepthFirstEnd::Exhausted { cut_off: false } => break (None, false),
+            DepthFirstEnd::Exhausted { cut_off: true } => {
+                if search_data.max_depth().is_some_and(|max| limit >= max) {
+                    break (None, true);
+                }
+                // CADA ITERACIÓN EMPIEZA CON UN ÁRBOL QUE SOLO TIENE LA RAÍZ
+                dfs.tree.truncate(root + 1);
+                limit += 1;
+            }
+        }
+    };
+    SearchResult::from_node(&dfs.tree, found, depth_limited, dfs.stats, dfs.started)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crossteaser::crossteaser_search::*;
+    use crate::search::astar::tests::*;
+    use crate::search::astar::*;
+    use crate::search::iterative_deepening::*;
+    use crate::search::tests::*;
+
+    #[test]
+    fn iterative_deepening_test() {
+        let result = iterative_deepening_search(vec![], &DummySearch {});
+        assert!(result.is_found());
+        assert_eq!(result.cost, 4);
+        assert_eq!(result.actions, vec![0, 1, 2, 3]);
+        assert_eq!(result.path[0], Vec::<i32>::new());
+        // LAS ITERACIONES CON LÍMITE 0, 1, 2 Y 3 EXPANDEN TODO LO QUE HAY POR ENCIMA DEL LÍMITE
+        let previous = 1 + (1 + 4) + (1 + 4 + 16);
+        // LA ÚLTIMA EXPANDE LA RAÍZ, [0], [0 0] Y SUS 4 HIJOS, [0 1], [0 1 0], [0 1 1] Y [0 1 2]
+        assert_eq!(result.stats.expanded, previous + 11);
+    }
+
+    #[test]
+    fn iterative_deepening_without_goal_is_exhausted() {
+        let result = iterative_deepening_search(vec![], &NoGoal {});
+        assert_eq!(result.outcome, SearchOutcome::Exhausted);
+        assert!(result.path.is_empty());
+    }
+
+    #[test]
+    fn iterative_deepening_finds_fewest_moves() {
+        let search = SearchInGraph {
+            edges: vec![
+                ('S', 'A', 1),
+                ('A', 'S', 1),
+                ('A', 'B', 1),
+                ('B', 'A', 1),
+                ('B', 'G', 1),
+                ('S', 'G', 9),
+            ],
+            heuristic: vec![],
+            goal: 'G',
+        };
+        let result = iterative_deepening_search('S', &search);
+        assert_eq!(result.path, vec!['S', 'G']);
+        assert_eq!(result.cost, 9);
+    }
+
+    #[test]
+    fn iterative_deepening_respects_max_depth() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let search = BoardSearchWithGoal {
+            goal: board,
+            max_depth: Some(3),
+        };
+        let result = iterative_deepening_search(scrambled_board(&board, 20), &search);
+        assert_eq!(result.outcome, SearchOutcome::DepthLimit);
+    }
+
+    #[test]
+    fn iterative_deepening_on_scrambled_board() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let search = BoardSearchWithGoal {
+            goal: board,
+            max_depth: None,
+        };
+
+        for step in 1..10 {
+            let scrambled = scrambled_board(&board, step);
+            let result = iterative_deepening_search(scrambled, &search);
+            let a_star_result = a_star_search(scrambled, &search);
+            println!(
+                "step:{} id:{} a*:{} expanded:{}",
+                step, result.cost, a_star_result.cost, result.stats.expanded
+            );
+            assert_eq!(result.cost, a_star_result.cost);
+            assert_eq!(
+                scrambled
+                    .apply_moves_to_empty_position_get_last(&result.actions)
+                    .unwrap(),
+                board
+            );
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::search::SearchStats;
+
+/// Flag shared between a running search and whoever wants to stop it, possibly from
+/// another thread. Clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Budgets for a search. A search that goes over any of them stops with
+/// `SearchOutcome::BudgetExceeded`. `None` means no limit.
+#[derive(Debug, Clone, Default)]
+pub struct SearchLimits {
+    pub max_expanded: Option<usize>,
+    pub max_time: Option<Duration>,
+    /// Most nodes in the closed set. The algorithms without one (depth first, IDA*,
+    /// breadth first) count the nodes they keep in memory instead.
+    pub max_closed: Option<usize>,
+    pub cancel: Option<CancelToken>,
+}
+
+impl SearchLimits {
+    pub fn none() -> Self {
+        SearchLimits::default()
+    }
+
+    /// Checked by the algorithms before expanding each node.
+    pub(crate) fn exceeded(&self, stats: &SearchStats, closed: usize, started: Instant) -> bool {
+        if let Some(max) = self.max_expanded {
+            if stats.expanded >= max {
+                return true;
+            }
+        }
+        if let Some(max) = self.max_closed {
+            if closed >= max {
+                return true;
+            }
+        }
+        if let Some(cancel) = &self.cancel {
+            if cancel.is_cancelled() {
+                return true;
+            }
+        }
+        if let Some(max) = self.max_time {
+            if started.elapsed() >= max {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search::astar::tests::*;
+    use crate::search::astar::*;
+    use crate::search::bidirectional::*;
+    use crate::search::idastar::*;
+    use crate::search::iterative_deepening::*;
+    use crate::search::limits::*;
+    use crate::search::uniform_cost::*;
+    use crate::search::*;
+
+    fn expanded(max: usize) -> SearchLimits {
+        SearchLimits {
+            max_expanded: Some(max),
+            ..SearchLimits::none()
+        }
+    }
+
+    #[test]
+    fn a_star_stops_at_max_expanded() {
+        let search = SearchToGoal {
+            goal: Vector(50, 50),
+        };
+        let result = a_star_search_with(Vector(0, 0), &search, &mut NoObserver, &expanded(10));
+
+        assert_eq!(result.outcome, SearchOutcome::BudgetExceeded);
+        assert_eq!(result.stats.expanded, 10);
+        assert!(result.goal().is_none());
+
+        // EL MEJOR NODO ES EL MÁS CERCANO AL OBJETIVO SEGÚN LA HEURÍSTICA
+        let best = result.best().unwrap();
+        println!("best:{} cost:{}", best, result.cost);
+        assert_eq!(result.path[0], Vector(0, 0));
+        assert_eq!(result.cost, result.path.len() as u64 - 1);
+        assert_eq!(result.actions.len(), result.path.len() - 1);
+        assert!(search.heuristic(best) < search.heuristic(&Vector(0, 0)));
+    }
+
+    #[test]
+    fn every_algorithm_stops_at_max_expanded() {
+        type Run<'a> = Box<dyn Fn(&SearchLimits) -> SearchResult<Vector, Direction> + 'a>;
+
+        let search = SearchToGoal {
+            goal: Vector(50, 50),
+        };
+        let root = Vector(0, 0);
+        let runs: Vec<(&str, Run)> = vec![
+            (
+                "dfs",
+                Box::new(|l| deep_first_search_with(root.clone(), &search, &mut NoObserver, l)),
+            ),
+            (
+                "bfs",
+                Box::new(|l| breadth_first_search_with(root.clone(), &search, &mut NoObserver, l)),
+            ),
+            (
+                "a*",
+                Box::new(|l| a_star_search_with(root.clone(), &search, &mut NoObserver, l)),
+            ),
+            (
+                "ida*",
+                Box::new(|l| ida_star_search_with(root.clone(), &search, &mut NoObserver, l)),
+            ),
+            (
+                "ucs",
+                Box::new(|l| uniform_cost_search_with(root.clone(), &search, &mut NoObserver, l)),
+            ),
+            (
+                "iddfs",
+                Box::new(|l| {
+                    iterative_deepening_search_with(root.clone(), &search, &mut NoObserver, l)
+                }),
+            ),
+            (
+                "bidirectional",
+                Box::new(|l| {
+                    bidirectional_search_with(
+                        root.clone(),
+                        search.goal.clone(),
+                        &search,
+                        &mut NoObserver,
+                        l,
+                    )
+                }),
+            ),
+        ];
+        for (name, run) in runs.iter() {
+            let result = run(&expanded(10));
+            println!("{}: {:?} {:?}", name, result.best(), result.stats);
+            assert_eq!(result.outcome, SearchOutcome::BudgetExceeded, "{}", name);
+            assert_eq!(result.stats.expanded, 10, "{}", name);
+            assert_eq!(result.path[0], root, "{}", name);
+        }
+    }
+
+    #[test]
+    fn a_star_stops_at_max_closed() {
+        let search = SearchToGoal {
+            goal: Vector(50, 50),
+        };
+        let limits = SearchLimits {
+            max_closed: Some(25),
+            ..SearchLimits::none()
+        };
+        let result = a_star_search_with(Vector(0, 0), &search, &mut NoObserver, &limits);
+        assert_eq!(result.outcome, SearchOutcome::BudgetExceeded);
+        assert_eq!(result.stats.expanded, 25);
+    }
+
+    #[test]
+    fn a_star_stops_at_max_time() {
+        // EL OBJETIVO QUEDA FUERA DEL TERRENO: HABRÍA QUE EXPANDIR UN MILLÓN DE CASILLAS
+        let search = SearchInTerrain {
+            terrain: vec![vec![1; 1000]; 1000],
+            goal: Vector(5000, 5000),
+        };
+        let limits = SearchLimits {
+            max_time: Some(Duration::from_millis(50)),
+            ..SearchLimits::none()
+        };
+        let result = a_star_search_with(Vector(0, 0), &search, &mut NoObserver, &limits);
+        assert_eq!(result.outcome, SearchOutcome::BudgetExceeded);
+        assert!(result.stats.elapsed >= Duration::from_millis(50));
+        assert!(result.cost > 0);
+    }
+
+    #[test]
+    fn search_cancelled_from_another_thread() {
+        let search = SearchToGoal { goal: Vector(0, 0) };
+        let cancel = CancelToken::new();
+        let limits = SearchLimits {
+            cancel: Some(cancel.clone()),
+            ..SearchLimits::none()
+        };
+
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            cancel.cancel();
+        });
+        let result = uniform_cost_search_with(Vector(1, 1), &search, &mut NoObserver, &limits);
+        canceller.join().unwrap();
+
+        assert_eq!(result.outcome, SearchOutcome::BudgetExceeded);
+        assert!(result.stats.expanded > 0);
+    }
+}
@@ -0,0 +1,480 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::search::*;
+
+/// State of the automaton before any move.
+pub const START: usize = 0;
+
+/// Move sequences that never need to be explored, compiled into an automaton that
+/// follows the moves of a path. A path whose moves end with one of the sequences is
+/// pruned. The automaton has the sequences as a trie, with every missing transition
+/// filled in by the failure links, so following a move is a lookup.
+#[derive(Debug, Clone)]
+pub struct MovePruning<A> {
+    alphabet: Vec<A>,
+    indexes: HashMap<A, usize>,
+    sequences: Vec<Vec<usize>>,
+    // transitions[state][move]
+    transitions: Vec<Vec<usize>>,
+    pruned: Vec<bool>,
+}
+
+impl<A: Clone + Eq + Hash> MovePruning<A> {
+    /// Prunes every path with one of `sequences` among its moves.
+    pub fn from_sequences(sequences: &[Vec<A>]) -> Self {
+        let mut alphabet = Vec::new();
+        let mut indexes = HashMap::new();
+        let sequences = sequences
+            .iter()
+            .map(|sequence| {
+                sequence
+                    .iter()
+                    .map(|action| index_of(&mut alphabet, &mut indexes, action))
+                    .collect()
+            })
+            .collect();
+        Self::from_indexes(alphabet, indexes, sequences)
+    }
+
+    /// Learns the redundant sequences of at most `max_length` moves from the states
+    /// reached from `samples`. Moves are ordered by when they are first seen, and
+    /// sequences by length and then by their moves. A sequence is redundant when a
+    /// smaller sequence, the empty one included, costs no more and reaches the same
+    /// state. Only the sequences redundant from every sample where they apply are
+    /// kept, and the pruning is only as safe as the samples are representative.
+    pub fn learn<T: State, S: SearchInfo<T, Action = A> + ?Sized>(
+        search_data: &S,
+        samples: &[T],
+        max_length: usize,
+    ) -> Self {
+        let mut alphabet = Vec::new();
+        let mut indexes = HashMap::new();
+        let mut redundant: HashSet<Vec<usize>> = HashSet::new();
+        let mut needed: HashSet<Vec<usize>> = HashSet::new();
+
+        for sample in samples.iter() {
+            // EL MENOR COSTE CON EL QUE SE HA LLEGADO A CADA ESTADO, EN ORDEN DE SECUENCIAS
+            let mut best: HashMap<T, u64> = HashMap::new();
+            best.insert(sample.clone(), 0);
+            let mut level: Vec<(Vec<usize>, T, u64)> = vec![(Vec::new(), sample.clone(), 0)];
+
+            for _ in 0..max_length {
+                let mut next_level = Vec::new();
+                for (sequence, state, cost) in level.iter() {
+                    let mut children: Vec<(usize, T)> = search_data
+                        .expand_state(state)
+                        .into_iter()
+                        .map(|(action, child)| {
+                            (index_of(&mut alphabet, &mut indexes, &action), child)
+                        })
+                        .collect();
+                    children.sort_by_key(|(index, _)| *index);
+
+                    for (index, child) in children {
+                        let child_cost = cost + search_data.step_cost(state, &child);
+                        let mut child_sequence = sequence.clone();
+                        child_sequence.push(index);
+                        match best.get(&child) {
+                            Some(&previous) if previous <= child_cost => {
+                                // SUS CONTINUACIONES YA QUEDAN PODADAS CON ELLA
+                                redundant.insert(child_sequence);
+                            }
+                            _ => {
+                                best.insert(child.clone(), child_cost);
+                                needed.insert(child_sequence.clone());
+                                next_level.push((child_sequence, child, child_cost));
+                            }
+                        }
+                    }
+                }
+                level = next_level;
+            }
+        }
+
+        let mut sequences: Vec<Vec<usize>> = redundant.difference(&needed).cloned().collect();
+        sequences.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        // LAS QUE CONTIENEN A OTRA MÁS CORTA YA QUEDAN PODADAS CON ELLA
+        let mut minimal: Vec<Vec<usize>> = Vec::new();
+        for sequence in sequences {
+            let contains = |shorter: &Vec<usize>| {
+                sequence
+                    .windows(shorter.len())
+                    .any(|window| window == &shorter[..])
+            };
+            if !minimal.iter().any(contains) {
+                minimal.push(sequence);
+            }
+        }
+        Self::from_indexes(alphabet, indexes, minimal)
+    }
+
+    fn from_indexes(
+        alphabet: Vec<A>,
+        indexes: HashMap<A, usize>,
+        sequences: Vec<Vec<usize>>,
+    ) -> Self {
+        let moves = alphabet.len();
+        // EL TRIE DE LAS SECUENCIAS
+        let mut trie: Vec<Vec<Option<usize>>> = vec![vec![None; moves]];
+        let mut pruned = vec![false];
+        for sequence in sequences.iter() {
+            let mut node = START;
+            for &index in sequence.iter() {
+                node = match trie[node][index] {
+                    Some(next) => next,
+                    None => {
+                        trie.push(vec![None; moves]);
+                        pruned.push(false);
+                        trie[node][index] = Some(trie.len() - 1);
+                        trie.len() - 1
+                    }
+                };
+            }
+            pruned[node] = true;
+        }
+
+        // LOS ENLACES DE FALLO EN ANCHURA: CADA NODO HEREDA LAS TRANSICIONES QUE LE FALTAN
+        let mut transitions = vec![vec![START; moves]; trie.len()];
+        let mut failure = vec![START; trie.len()];
+        let mut queue = VecDeque::new();
+        for index in 0..moves {
+            if let Some(child) = trie[START][index] {
+                transitions[START][index] = child;
+                queue.push_back(child);
+            }
+        }
+        while let Some(node) = queue.pop_front() {
+            pruned[node] = pruned[node] || pruned[failure[node]];
+            for index in 0..moves {
+                match trie[node][index] {
+                    Some(child) => {
+                        failure[child] = transitions[failure[node]][index];
+                        transitions[node][index] = child;
+                        queue.push_back(child);
+                    }
+                    None => transitions[node][index] = transitions[failure[node]][index],
+                }
+            }
+        }
+
+        MovePruning {
+            alphabet,
+            indexes,
+            sequences,
+            transitions,
+            pruned,
+        }
+    }
+
+    /// The state after `action`, or `None` if the path is pruned. A move that is not
+    /// in any sequence goes back to `START`.
+    pub fn next(&self, state: usize, action: &A) -> Option<usize> {
+        let next = match self.indexes.get(action) {
+            Some(&index) => self.transitions[state][index],
+            None => START,
+        };
+        if self.pruned[next] {
+            None
+        } else {
+            Some(next)
+        }
+    }
+
+    /// The pruned sequences, shortest first.
+    pub fn sequences(&self) -> Vec<Vec<A>> {
+        self.sequences
+            .iter()
+            .map(|sequence| {
+                sequence
+                    .iter()
+                    .map(|&index| self.alphabet[index].clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// States of the automaton, the pruned ones included.
+    pub fn states(&self) -> usize {
+        self.transitions.len()
+    }
+}
+
+fn index_of<A: Clone + Eq + Hash>(
+    alphabet: &mut Vec<A>,
+    indexes: &mut HashMap<A, usize>,
+    action: &A,
+) -> usize {
+    if let Some(&index) = indexes.get(action) {
+        return index;
+    }
+    alphabet.push(action.clone());
+    indexes.insert(action.clone(), alphabet.len() - 1);
+    alphabet.len() - 1
+}
+
+/// A state along with the state of the automaton on the path that reached it. Two of
+/// them are equal if their states are, so the cycle checks of the depth first searches
+/// still work on the states alone.
+#[derive(Debug, Clone)]
+pub struct Pruned<T> {
+    pub state: T,
+    pub automaton: usize,
+}
+
+impl<T> Pruned<T> {
+    pub fn new(state: T) -> Self {
+        Pruned {
+            state,
+            automaton: START,
+        }
+    }
+}
+
+impl<T: PartialEq> PartialEq for Pruned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.state == other.state
+    }
+}
+
+impl<T: Eq> Eq for Pruned<T> {}
+
+impl<T: Hash> Hash for Pruned<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.state.hash(state);
+    }
+}
+
+impl<T: State> State for Pruned<T> {}
+
+impl<T: Display> Display for Pruned<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.state.fmt(f)
+    }
+}
+
+/// Any search without the successors that `pruning` prunes. Meant for the tree
+/// searches, like IDA* and the depth first ones: a search that closes states keeps
+/// the first path to each one, and the pruning after another path could be wrong.
+#[derive(Debug)]
+pub struct PrunedSearch<'m, S, A> {
+    pub delegate: S,
+    pub pruning: &'m MovePruning<A>,
+}
+
+impl<'m, T, A, S> SearchInfo<Pruned<T>> for PrunedSearch<'m, S, A>
+where
+    T: State,
+    A: Clone + Eq + Hash + std::fmt::Debug,
+    S: SearchInfo<T, Action = A>,
+{
+    type Action = A;
+
+    fn heuristic(&self, state: &Pruned<T>) -> u64 {
+        self.delegate.heuristic(&state.state)
+    }
+    fn max_depth(&self) -> Option<u64> {
+        self.delegate.max_depth()
+    }
+    fn step_cost(&self, from: &Pruned<T>, to: &Pruned<T>) -> u64 {
+        self.delegate.step_cost(&from.state, &to.state)
+    }
+    fn tie_break(&self) -> TieBreak {
+        self.delegate.tie_break()
+    }
+    fn expand_state(&self, state: &Pruned<T>) -> Vec<(A, Pruned<T>)> {
+        self.delegate
+            .expand_state(&state.state)
+            .into_iter()
+            .filter_map(|(action, child)| {
+                self.pruning
+                    .next(state.automaton, &action)
+                    .map(|automaton| {
+                        (
+                            action,
+                            Pruned {
+                                state: child,
+                                automaton,
+                            },
+                        )
+                    })
+            })
+            .collect()
+    }
+    fn is_goal(&self, state: &Pruned<T>) -> bool {
+        self.delegate.is_goal(&state.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crossteaser::crossteaser_search::tests::scrambled_samples;
+    use crate::crossteaser::crossteaser_search::*;
+    use crate::search::astar::tests::*;
+    use crate::search::idastar::*;
+    use crate::search::iterative_deepening::*;
+    use crate::search::move_pruning::*;
+
+    fn open_terrain(size: usize) -> SearchInTerrain {
+        SearchInTerrain {
+            terrain: vec![vec![1; size]; size],
+            goal: Vector(size as u64 - 1, size as u64 - 1),
+        }
+    }
+
+    #[test]
+    fn automaton_follows_the_sequences() {
+        use Direction::*;
+        let pruning = MovePruning::from_sequences(&[vec![North, South], vec![East, North, East]]);
+        // LA RAÍZ, N, NS, E, EN, ENE
+        assert_eq!(pruning.states(), 6);
+
+        let follow = |moves: &[Direction]| {
+            moves
+                .iter()
+                .try_fold(START, |state, action| pruning.next(state, action))
+        };
+        assert_eq!(follow(&[North, South]), None);
+        assert_eq!(follow(&[East, North, East]), None);
+        // LA SEGUNDA SECUENCIA EMPIEZA A MITAD DE LA PRIMERA
+        assert_eq!(follow(&[East, East, North, East]), None);
+        assert_eq!(follow(&[East, North, South]), None);
+        assert!(follow(&[North, East, North, West, South]).is_some());
+        // UN MOVIMIENTO QUE NO APARECE VUELVE AL PRINCIPIO
+        assert_eq!(follow(&[East, West]), Some(START));
+    }
+
+    #[test]
+    fn learns_commuting_and_undoing_moves() {
+        use Direction::*;
+        let search = open_terrain(5);
+        let pruning = MovePruning::learn(&search, &[Vector(2, 2)], 2);
+        // LOS MOVIMIENTOS SE ORDENAN COMO LOS DA expand_state: WEST, NORTH, EAST, SOUTH
+        let mut sequences = pruning.sequences();
+        sequences.sort_by_key(|sequence| format!("{:?}", sequence));
+        let mut expected = vec![
+            vec![North, South],
+            vec![South, North],
+            vec![East, West],
+            vec![West, East],
+            vec![North, West],
+            vec![South, West],
+            vec![East, North],
+            vec![South, East],
+        ];
+        expected.sort_by_key(|sequence| format!("{:?}", sequence));
+        assert_eq!(sequences, expected);
+    }
+
+    #[test]
+    fn cheaper_sequences_are_kept() {
+        use Direction::*;
+        // AL NORTE DEL CENTRO HAY UNA CASILLA CARA: IR AL ESTE Y LUEGO AL NORTE SALE MÁS BARATO
+        let mut search = open_terrain(5);
+        let center = MovePruning::learn(&search, &[Vector(2, 2)], 2);
+        search.terrain[1][2] = 9;
+        let weighted = MovePruning::learn(&search, &[Vector(2, 2)], 2);
+        assert!(center.sequences().contains(&vec![East, North]));
+        assert!(!weighted.sequences().contains(&vec![East, North]));
+        // LOS DEMÁS SIGUEN PODADOS
+        assert_eq!(weighted.sequences().len(), center.sequences().len() - 1);
+    }
+
+    #[test]
+    fn pruned_searches_in_terrain() {
+        let search = open_terrain(6);
+        let pruning = MovePruning::learn(&search, &[Vector(3, 3)], 3);
+        let pruned = PrunedSearch {
+            delegate: &search,
+            pruning: &pruning,
+        };
+
+        let ida = ida_star_search(Vector(0, 0), &search);
+        let pruned_ida = ida_star_search(Pruned::new(Vector(0, 0)), &pruned);
+        println!(
+            "IDA*: {} generados, {} con poda",
+            ida.stats.generated, pruned_ida.stats.generated
+        );
+        assert!(pruned_ida.is_found());
+        assert_eq!(pruned_ida.cost, ida.cost);
+        assert!(pruned_ida.stats.generated < ida.stats.generated);
+
+        let iterative = iterative_deepening_search(Vector(0, 0), &search);
+        let pruned_iterative = iterative_deepening_search(Pruned::new(Vector(0, 0)), &pruned);
+        println!(
+            "Profundidad iterativa: {} expandidos, {} con poda",
+            iterative.stats.expanded, pruned_iterative.stats.expanded
+        );
+        assert_eq!(pruned_iterative.cost, iterative.cost);
+        // SOLO QUEDA UN CAMINO A CADA CASILLA
+        assert!(pruned_iterative.stats.expanded * 10 < iterative.stats.expanded);
+    }
+
+    fn crossteaser_board(piece_set: &PieceSet) -> Board<'_> {
+        Board::from_piece(piece_set, piece_set.get_piece_index_of_initial_piece())
+    }
+
+    #[test]
+    fn learns_undoing_moves_in_crossteaser() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let goal = crossteaser_board(&piece_set);
+        let search = BoardSearchWithGoal {
+            goal,
+            max_depth: None,
+        };
+        let samples = scrambled_samples(&goal, 5, 30);
+        let pruning = MovePruning::learn(&search, &samples, 6);
+        println!("{:?}", pruning.sequences());
+        // NINGUNA SECUENCIA CORTA DEJA EL TABLERO IGUAL QUE OTRA: SOLO SE PODAN LAS VUELTAS ATRÁS
+        assert_eq!(pruning.sequences().len(), 4);
+        for direction in Direction::posible_rotations().iter() {
+            assert!(pruning
+                .sequences()
+                .contains(&vec![*direction, direction.opposite()]));
+        }
+    }
+
+    #[test]
+    fn pruned_searches_in_crossteaser() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let goal = crossteaser_board(&piece_set);
+        let samples = scrambled_samples(&goal, 5, 30);
+        let search = BoardSearchWithGoal {
+            goal,
+            max_depth: None,
+        };
+        let pruning = MovePruning::learn(&search, &samples, 4);
+        let pruned = PrunedSearch {
+            delegate: &search,
+            pruning: &pruning,
+        };
+
+        let board = scrambled_board(&goal, 20);
+        let ida = ida_star_search(board, &search);
+        let pruned_ida = ida_star_search(Pruned::new(board), &pruned);
+        println!(
+            "IDA*: {} generados, {} con poda",
+            ida.stats.generated, pruned_ida.stats.generated
+        );
+        assert!(pruned_ida.is_found());
+        assert_eq!(pruned_ida.cost, ida.cost);
+        assert!(pruned_ida.stats.generated < ida.stats.generated);
+
+        // SIN OBJETIVO AL ALCANCE SE RECORRE TODO EL ÁRBOL HASTA LA PROFUNDIDAD
+        let limited = BoardSearchWithGoal {
+            goal: scrambled_board(&goal, 40),
+            max_depth: Some(6),
+        };
+        let pruned_limited = PrunedSearch {
+            delegate: &limited,
+            pruning: &pruning,
+        };
+        let dfs = deep_first_search(goal, &limited);
+        let pruned_dfs = deep_first_search(Pruned::new(goal), &pruned_limited);
+        println!(
+            "Profundidad: {} generados, {} con poda",
+            dfs.stats.generated, pruned_dfs.stats.generated
+        );
+        assert!(!dfs.is_found() && !pruned_dfs.is_found());
+        assert!(pruned_dfs.stats.generated < dfs.stats.generated);
+    }
+}
@@ -0,0 +1,249 @@
+use std::time::Instant;
+
+use crate::search::*;
+
+/// Expansions between two calls to `SearchObserver::progress`.
+pub const PROGRESS_INTERVAL: usize = 10000;
+
+/// What an observer sees of a node: its state, its depth and its f = g + h.
+#[derive(Debug)]
+pub struct ObservedNode<'n, T> {
+    pub state: &'n T,
+    pub level: u64,
+    pub g: u64,
+    pub h: u64,
+}
+
+impl<'n, T> Clone for ObservedNode<'n, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'n, T> Copy for ObservedNode<'n, T> {}
+
+impl<'n, T> ObservedNode<'n, T> {
+    pub fn f(&self) -> u64 {
+        self.g + self.h
+    }
+}
+
+impl<T: State, A> SearchNode<T, A> {
+    pub fn observed(&self) -> ObservedNode<'_, T> {
+        ObservedNode {
+            state: &self.state,
+            level: self.level,
+            g: self.cost,
+            h: self.heuristic,
+        }
+    }
+}
+
+/// Callbacks called by the search algorithms while they run. Every callback does
+/// nothing by default, so an observer only implements the ones it needs.
+pub trait SearchObserver<T> {
+    /// A node was created, the root included. It may be discarded later as a duplicate.
+    fn generated(&mut self, _node: ObservedNode<T>) {}
+    /// The children of a node were generated.
+    fn expanded(&mut self, _node: ObservedNode<T>) {}
+    /// The node returned by the search.
+    fn goal_found(&mut self, _node: ObservedNode<T>) {}
+    /// A node was not expanded because it is at `max_depth`.
+    fn pruned_by_depth(&mut self, _node: ObservedNode<T>) {}
+    /// Called every `PROGRESS_INTERVAL` expansions.
+    fn progress(&mut self, _stats: &SearchStats) {}
+}
+
+/// The observer used when none is given.
+#[derive(Debug, Default)]
+pub struct NoObserver;
+
+impl<T> SearchObserver<T> for NoObserver {}
+
+/// Prints the stats of the search on every progress call.
+#[derive(Debug, Default)]
+pub struct ProgressReporter;
+
+impl<T> SearchObserver<T> for ProgressReporter {
+    fn progress(&mut self, stats: &SearchStats) {
+        println!(
+            "Nodos expandidos: {} Nodos generados: {} Frontera máxima: {} Nivel máximo: {} Tiempo: {:?}",
+            stats.expanded,
+            stats.generated,
+            stats.peak_frontier,
+            stats.max_depth_reached,
+            stats.elapsed
+        );
+    }
+}
+
+/// Calls `progress` when the number of expanded nodes reaches a multiple of
+/// `PROGRESS_INTERVAL`. Called after every expansion.
+pub(crate) fn report_progress<T>(
+    observer: &mut dyn SearchObserver<T>,
+    stats: &mut SearchStats,
+    started: Instant,
+) {
+    if stats.expanded.is_multiple_of(PROGRESS_INTERVAL) {
+        stats.elapsed = started.elapsed();
+        observer.progress(stats);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crossteaser::crossteaser_search::*;
+    use crate::search::astar::tests::*;
+    use crate::search::astar::*;
+    use crate::search::bidirectional::*;
+    use crate::search::idastar::*;
+    use crate::search::iterative_deepening::*;
+    use crate::search::observer::*;
+    use crate::search::uniform_cost::*;
+
+    #[derive(Debug)]
+    struct Recorder<T> {
+        generated: usize,
+        expanded: Vec<T>,
+        goals: Vec<(T, u64)>,
+        pruned: Vec<u64>,
+        progress: Vec<usize>,
+    }
+
+    impl<T> Recorder<T> {
+        fn new() -> Self {
+            Recorder {
+                generated: 0,
+                expanded: Vec::new(),
+                goals: Vec::new(),
+                pruned: Vec::new(),
+                progress: Vec::new(),
+            }
+        }
+    }
+
+    impl<T: Clone> SearchObserver<T> for Recorder<T> {
+        fn generated(&mut self, _node: ObservedNode<T>) {
+            self.generated += 1;
+        }
+        fn expanded(&mut self, node: ObservedNode<T>) {
+            self.expanded.push(node.state.clone());
+        }
+        fn goal_found(&mut self, node: ObservedNode<T>) {
+            self.goals.push((node.state.clone(), node.g));
+        }
+        fn pruned_by_depth(&mut self, node: ObservedNode<T>) {
+            self.pruned.push(node.level);
+        }
+        fn progress(&mut self, stats: &SearchStats) {
+            self.progress.push(stats.expanded);
+        }
+    }
+
+    #[test]
+    fn observer_sees_what_stats_count() {
+        let search = inconsistent_graph();
+        let mut recorder = Recorder::new();
+        let result = a_star_search_with('S', &search, &mut recorder, &SearchLimits::none());
+
+        assert_eq!(recorder.generated, result.stats.generated);
+        assert_eq!(recorder.expanded.len(), result.stats.expanded);
+        assert_eq!(recorder.expanded[0], 'S');
+        assert_eq!(recorder.goals, vec![('G', 7)]);
+        assert!(recorder.pruned.is_empty());
+    }
+
+    #[test]
+    fn observer_is_called_by_every_algorithm() {
+        type Run<'a> = Box<dyn Fn(&mut Recorder<char>) -> SearchResult<char, char> + 'a>;
+
+        let search = inconsistent_graph();
+        let runs: Vec<(&str, Run)> = vec![
+            (
+                "dfs",
+                Box::new(|r| deep_first_search_with('S', &search, r, &SearchLimits::none())),
+            ),
+            (
+                "bfs",
+                Box::new(|r| breadth_first_search_with('S', &search, r, &SearchLimits::none())),
+            ),
+            (
+                "a*",
+                Box::new(|r| a_star_search_with('S', &search, r, &SearchLimits::none())),
+            ),
+            (
+                "ida*",
+                Box::new(|r| ida_star_search_with('S', &search, r, &SearchLimits::none())),
+            ),
+            (
+                "ucs",
+                Box::new(|r| uniform_cost_search_with('S', &search, r, &SearchLimits::none())),
+            ),
+            (
+                "iddfs",
+                Box::new(|r| {
+                    iterative_deepening_search_with('S', &search, r, &SearchLimits::none())
+                }),
+            ),
+        ];
+        for (name, run) in runs.iter() {
+            let mut recorder = Recorder::new();
+            let result = run(&mut recorder);
+            println!("{}: {:?}", name, result.stats);
+            assert!(result.is_found(), "{}", name);
+            assert_eq!(recorder.generated, result.stats.generated, "{}", name);
+            assert_eq!(recorder.expanded.len(), result.stats.expanded, "{}", name);
+            assert_eq!(recorder.goals, vec![('G', result.cost)], "{}", name);
+        }
+    }
+
+    #[test]
+    fn observer_in_bidirectional_search() {
+        let search = SearchToGoal { goal: Vector(3, 4) };
+        let mut recorder = Recorder::new();
+        let result = bidirectional_search_with(
+            Vector(0, 0),
+            Vector(3, 4),
+            &search,
+            &mut recorder,
+            &SearchLimits::none(),
+        );
+
+        assert_eq!(recorder.generated, result.stats.generated);
+        assert_eq!(recorder.expanded.len(), result.stats.expanded);
+        assert_eq!(recorder.goals.len(), 1);
+    }
+
+    #[test]
+    fn observer_sees_depth_pruning() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let search = BoardSearchWithGoal {
+            goal: Board::from_piece(&piece_set, 0),
+            max_depth: Some(2),
+        };
+        let board = Board::from_piece(&piece_set, 1);
+        let mut recorder = Recorder::new();
+        let result = a_star_search_with(board, &search, &mut recorder, &SearchLimits::none());
+
+        assert_eq!(result.outcome, SearchOutcome::DepthLimit);
+        assert!(recorder.goals.is_empty());
+        assert!(!recorder.pruned.is_empty());
+        assert!(recorder.pruned.iter().all(|level| *level == 2));
+    }
+
+    #[test]
+    fn observer_receives_progress() {
+        // SIN HEURÍSTICA SE EXPANDEN TODOS LOS ESTADOS MÁS CERCANOS QUE EL OBJETIVO
+        let search = SearchToGoal {
+            goal: Vector(150, 150),
+        };
+        let mut recorder = Recorder::new();
+        let result =
+            uniform_cost_search_with(Vector(0, 0), &search, &mut recorder, &SearchLimits::none());
+
+        let calls = result.stats.expanded / PROGRESS_INTERVAL;
+        assert!(calls >= 2);
+        let expected: Vec<usize> = (1..=calls).map(|i| i * PROGRESS_INTERVAL).collect();
+        assert_eq!(recorder.progress, expected);
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Order among nodes with the same f.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreak {
+    /// The node with the highest cost first, the one the heuristic sees closest to the goal.
+    HighestCost,
+    /// The node with the lowest cost first.
+    LowestCost,
+    /// The node pushed first.
+    Fifo,
+    /// The node pushed last.
+    Lifo,
+}
+
+struct Entry<K, V> {
+    priority: (u64, u64, u64),
+    position: usize,
+    key: K,
+    value: V,
+}
+
+/// Binary heap of values ordered by a precomputed f, with an index from key to a
+/// handle so a key can be found and its priority lowered in O(log n). The heap only
+/// moves handles around, so sifting never hashes keys.
+pub struct OpenList<K: Hash + Eq + Clone, V> {
+    heap: Vec<usize>,
+    entries: Vec<Option<Entry<K, V>>>,
+    free_entries: Vec<usize>,
+    index: HashMap<K, usize>,
+    tie_break: TieBreak,
+    counter: u64,
+}
+
+impl<K: Hash + Eq + Clone, V> OpenList<K, V> {
+    pub fn new(tie_break: TieBreak) -> Self {
+        OpenList {
+            heap: Vec::new(),
+            entries: Vec::new(),
+            free_entries: Vec::new(),
+            index: HashMap::new(),
+            tie_break,
+            counter: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.index.contains_key(key)
+    }
+
+    fn entry(&self, handle: usize) -> &Entry<K, V> {
+        self.entries[handle].as_ref().unwrap()
+    }
+
+    fn entry_mut(&mut self, handle: usize) -> &mut Entry<K, V> {
+        self.entries[handle].as_mut().unwrap()
+    }
+
+    fn priority_at(&self, position: usize) -> (u64, u64, u64) {
+        self.entry(self.heap[position]).priority
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.index.get(key).map(|h| &self.entry(*h).value)
+    }
+
+    /// f of the value stored for `key`.
+    pub fn f(&self, key: &K) -> Option<u64> {
+        self.index.get(key).map(|h| self.entry(*h).priority.0)
+    }
+
+    pub fn peek(&self) -> Option<(&K, &V)> {
+        self.heap.first().map(|h| {
+            let e = self.entry(*h);
+            (&e.key, &e.value)
+        })
+    }
+
+    /// Values in heap order, not in priority order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.heap.iter().map(move |h| {
+            let e = self.entry(*h);
+            (&e.key, &e.value)
+        })
+    }
+
+    fn priority(&mut self, f: u64, cost: u64) -> (u64, u64, u64) {
+        self.counter += 1;
+        let tie = match self.tie_break {
+            TieBreak::HighestCost => u64::MAX - cost,
+            TieBreak::LowestCost => cost,
+            TieBreak::Fifo => self.counter,
+            TieBreak::Lifo => u64::MAX - self.counter,
+        };
+        (f, tie, self.counter)
+    }
+
+    /// Adds `value` under `key`, replacing the value stored for `key` if there was one.
+    pub fn push(&mut self, key: K, f: u64, cost: u64, value: V) {
+        let priority = self.priority(f, cost);
+        if let Some(&handle) = self.index.get(&key) {
+            let entry = self.entry_mut(handle);
+            let old = entry.priority;
+            entry.priority = priority;
+            entry.value = value;
+            let position = entry.position;
+            if priority < old {
+                self.sift_up(position);
+            } else {
+                self.sift_down(position);
+            }
+            return;
+        }
+
+        let position = self.heap.len();
+        let entry = Some(Entry {
+            priority,
+            position,
+            key: key.clone(),
+            value,
+        });
+        let handle = match self.free_entries.pop() {
+            Some(handle) => {
+                self.entries[handle] = entry;
+                handle
+            }
+            None => {
+                self.entries.push(entry);
+                self.entries.len() - 1
+            }
+        };
+        self.index.insert(key, handle);
+        self.heap.push(handle);
+        self.sift_up(position);
+    }
+
+    /// Replaces the value stored for `key` only if the new f is lower, or equal with a
+    /// better tie. Returns whether it was replaced.
+    pub fn decrease_key(&mut self, key: &K, f: u64, cost: u64, value: V) -> bool {
+        let handle = match self.index.get(key) {
+            Some(&handle) => handle,
+            None => return false,
+        };
+        let priority = self.priority(f, cost);
+        let entry = self.entry_mut(handle);
+        if (priority.0, priority.1) >= (entry.priority.0, entry.priority.1) {
+            return false;
+        }
+        entry.priority = priority;
+        entry.value = value;
+        let position = entry.position;
+        self.sift_up(position);
+        true
+    }
+
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        self.remove_at(0)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let position = self.entry(*self.index.get(key)?).position;
+        self.remove_at(position).map(|(_, v)| v)
+    }
+
+    fn remove_at(&mut self, position: usize) -> Option<(K, V)> {
+        if position >= self.heap.len() {
+            return None;
+        }
+        let last = self.heap.len() - 1;
+        self.swap(position, last);
+        let handle = self.heap.pop().unwrap();
+        let entry = self.entries[handle].take().unwrap();
+        self.free_entries.push(handle);
+        self.index.remove(&entry.key);
+        if position < self.heap.len() {
+            self.sift_down(position);
+            self.sift_up(position);
+        }
+        Some((entry.key, entry.value))
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        let (handle_a, handle_b) = (self.heap[a], self.heap[b]);
+        self.entry_mut(handle_a).position = a;
+        self.entry_mut(handle_b).position = b;
+    }
+
+    fn sift_up(&mut self, mut position: usize) {
+        while position > 0 {
+            let parent = (position - 1) / 2;
+            if self.priority_at(position) >= self.priority_at(parent) {
+                break;
+            }
+            self.swap(position, parent);
+            position = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut position: usize) {
+        loop {
+            let left = 2 * position + 1;
+            let right = left + 1;
+            let mut smallest = position;
+            if left < self.heap.len() && self.priority_at(left) < self.priority_at(smallest) {
+                smallest = left;
+            }
+            if right < self.heap.len() && self.priority_at(right) < self.priority_at(smallest) {
+                smallest = right;
+            }
+            if smallest == position {
+                break;
+            }
+            self.swap(position, smallest);
+            position = smallest;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search::open_list::*;
+
+    fn pop_all(open: &mut OpenList<char, u64>) -> Vec<char> {
+        let mut ret = Vec::new();
+        while let Some((key, _)) = open.pop() {
+            ret.push(key);
+        }
+        ret
+    }
+
+    #[test]
+    fn pops_by_f() {
+        let mut open = OpenList::new(TieBreak::Fifo);
+        for (key, f) in [('a', 5), ('b', 3), ('c', 9), ('d', 1), ('e', 4)].iter() {
+            open.push(*key, *f, 0, *f);
+        }
+        assert_eq!(open.len(), 5);
+        assert_eq!(open.peek(), Some((&'d', &1)));
+        assert_eq!(pop_all(&mut open), vec!['d', 'b', 'e', 'a', 'c']);
+        assert!(open.is_empty());
+    }
+
+    #[test]
+    fn decrease_key() {
+        let mut open = OpenList::new(TieBreak::Fifo);
+        open.push('a', 5, 0, 5);
+        open.push('b', 3, 0, 3);
+        open.push('c', 9, 0, 9);
+
+        assert!(open.decrease_key(&'c', 1, 0, 1));
+        assert!(!open.decrease_key(&'a', 7, 0, 7));
+        assert!(!open.decrease_key(&'z', 0, 0, 0));
+        assert_eq!(open.get(&'c'), Some(&1));
+        assert_eq!(open.f(&'a'), Some(5));
+        assert_eq!(pop_all(&mut open), vec!['c', 'b', 'a']);
+    }
+
+    #[test]
+    fn remove() {
+        let mut open = OpenList::new(TieBreak::Fifo);
+        for (key, f) in [('a', 5), ('b', 3), ('c', 9), ('d', 1), ('e', 4)].iter() {
+            open.push(*key, *f, 0, *f);
+        }
+        assert_eq!(open.remove(&'b'), Some(3));
+        assert_eq!(open.remove(&'b'), None);
+        assert!(!open.contains_key(&'b'));
+        assert_eq!(pop_all(&mut open), vec!['d', 'e', 'a', 'c']);
+    }
+
+    #[test]
+    fn tie_breaks() {
+        let cases = [
+            (TieBreak::Fifo, vec!['a', 'b', 'c']),
+            (TieBreak::Lifo, vec!['c', 'b', 'a']),
+            (TieBreak::HighestCost, vec!['b', 'c', 'a']),
+            (TieBreak::LowestCost, vec!['a', 'c', 'b']),
+        ];
+        for (tie_break, expected) in cases.iter() {
+            let mut open = OpenList::new(*tie_break);
+            open.push('a', 5, 1, 0);
+            open.push('b', 5, 3, 0);
+            open.push('c', 5, 2, 0);
+            assert_eq!(pop_all(&mut open), *expected, "{:?}", tie_break);
+        }
+    }
+}
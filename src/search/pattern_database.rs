@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use crate::search::bidirectional::*;
+use crate::search::open_list::*;
+use crate::search::*;
+
+/// Maps the states of a domain to smaller patterns, keeping only part of each state,
+/// and walks the moves between patterns backwards.
+pub trait Abstraction<T: State>: std::fmt::Debug {
+    type Pattern: Clone + Eq + Hash + std::fmt::Debug;
+
+    fn abstract_state(&self, state: &T) -> Self::Pattern;
+
+    /// The patterns with a move to `pattern`, each one with the cost of that move. It
+    /// must not exceed the cost of the moves it abstracts. To add several databases,
+    /// only the moves of what the pattern keeps may cost something, and each move of
+    /// the domain may cost in one of them.
+    fn predecessors(&self, pattern: &Self::Pattern) -> Vec<(u64, Self::Pattern)>;
+}
+
+/// A lower bound of the cost from a state to the goal.
+pub trait Heuristic<T: State>: std::fmt::Debug {
+    fn heuristic(&self, state: &T) -> u64;
+}
+
+/// The exact cost from every pattern to the abstract goal. Every path in the domain is
+/// a path between its patterns that costs no more, so it is an admissible heuristic.
+pub struct PatternDatabase<T: State, A: Abstraction<T>> {
+    abstraction: A,
+    distances: HashMap<A::Pattern, u64>,
+    state: PhantomData<T>,
+}
+
+impl<T: State, A: Abstraction<T>> PatternDatabase<T, A> {
+    /// Searches backwards from the patterns of `goals`, by cost, until every pattern
+    /// that reaches them has its distance: with moves of cost one it is a breadth first
+    /// search.
+    pub fn build(abstraction: A, goals: &[T]) -> Self {
+        let mut distances: HashMap<A::Pattern, u64> = HashMap::new();
+        let mut not_expanded: OpenList<A::Pattern, ()> = OpenList::new(TieBreak::Fifo);
+        for goal in goals.iter() {
+            let pattern = abstraction.abstract_state(goal);
+            distances.insert(pattern.clone(), 0);
+            not_expanded.push(pattern, 0, 0, ());
+        }
+
+        while let Some((pattern, ())) = not_expanded.pop() {
+            let distance = distances[&pattern];
+            for (cost, previous) in abstraction.predecessors(&pattern) {
+                let previous_distance = distance + cost;
+                if distances
+                    .get(&previous)
+                    .is_none_or(|known| previous_distance < *known)
+                {
+                    distances.insert(previous.clone(), previous_distance);
+                    not_expanded.push(previous, previous_distance, previous_distance, ());
+                }
+            }
+        }
+
+        PatternDatabase {
+            abstraction,
+            distances,
+            state: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.distances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.distances.is_empty()
+    }
+
+    /// The distance of the pattern of `state`, if it reaches the goal.
+    pub fn distance(&self, state: &T) -> Option<u64> {
+        self.distances
+            .get(&self.abstraction.abstract_state(state))
+            .cloned()
+    }
+}
+
+// LOS DERIVE PEDIRÍAN QUE T FUERA Debug
+impl<T: State, A: Abstraction<T>> std::fmt::Debug for PatternDatabase<T, A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "PatternDatabase {{ abstraction: {:?}, patterns: {} }}",
+            self.abstraction,
+            self.distances.len()
+        )
+    }
+}
+
+// UN PATRÓN QUE NO LLEGA AL OBJETIVO TAMPOCO LLEGA EN EL DOMINIO: CUALQUIER VALOR VALE
+impl<T: State, A: Abstraction<T>> Heuristic<T> for PatternDatabase<T, A> {
+    fn heuristic(&self, state: &T) -> u64 {
+        self.distance(state).unwrap_or(0)
+    }
+}
+
+/// The sum of heuristics whose abstractions split the cost of every move, like
+/// databases of disjoint groups of pieces.
+pub struct AdditiveHeuristic<'h, T: State> {
+    pub parts: Vec<&'h dyn Heuristic<T>>,
+}
+
+impl<'h, T: State> std::fmt::Debug for AdditiveHeuristic<'h, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdditiveHeuristic")
+            .field("parts", &self.parts)
+            .finish()
+    }
+}
+
+impl<'h, T: State> Heuristic<T> for AdditiveHeuristic<'h, T> {
+    fn heuristic(&self, state: &T) -> u64 {
+        self.parts.iter().map(|part| part.heuristic(state)).sum()
+    }
+}
+
+/// The maximum of admissible heuristics, which is admissible too.
+pub struct MaxHeuristic<'h, T: State> {
+    pub parts: Vec<&'h dyn Heuristic<T>>,
+}
+
+impl<'h, T: State> std::fmt::Debug for MaxHeuristic<'h, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MaxHeuristic")
+            .field("parts", &self.parts)
+            .finish()
+    }
+}
+
+impl<'h, T: State> Heuristic<T> for MaxHeuristic<'h, T> {
+    fn heuristic(&self, state: &T) -> u64 {
+        self.parts
+            .iter()
+            .map(|part| part.heuristic(state))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Any search with `heuristic` added: the heuristic of the search is the maximum of
+/// its own and that one. Everything else comes from the delegate.
+pub struct WithHeuristic<'h, T: State, S> {
+    pub delegate: S,
+    pub heuristic: &'h dyn Heuristic<T>,
+}
+
+impl<'h, T: State, S: std::fmt::Debug> std::fmt::Debug for WithHeuristic<'h, T, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WithHeuristic")
+            .field("delegate", &self.delegate)
+            .field("heuristic", &self.heuristic)
+            .finish()
+    }
+}
+
+impl<'h, T: State, S: SearchInfo<T>> SearchInfo<T> for WithHeuristic<'h, T, S> {
+    type Action = S::Action;
+
+    fn heuristic(&self, state: &T) -> u64 {
+        self.delegate
+            .heuristic(state)
+            .max(self.heuristic.heuristic(state))
+    }
+    fn max_depth(&self) -> Option<u64> {
+        self.delegate.max_depth()
+    }
+    fn step_cost(&self, from: &T, to: &T) -> u64 {
+        self.delegate.step_cost(from, to)
+    }
+    fn tie_break(&self) -> TieBreak {
+        self.delegate.tie_break()
+    }
+    fn expand_state(&self, state: &T) -> Vec<(S::Action, T)> {
+        self.delegate.expand_state(state)
+    }
+    fn is_goal(&self, state: &T) -> bool {
+        self.delegate.is_goal(state)
+    }
+}
+
+impl<'h, T: State, R: ReversibleSearchInfo<T>> ReversibleSearchInfo<T> for WithHeuristic<'h, T, R> {
+    fn predecessors(&self, state: &T) -> Vec<(R::Action, T)> {
+        self.delegate.predecessors(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::Entry;
+    use std::collections::HashMap;
+    use std::collections::VecDeque;
+
+    use crate::search::astar::*;
+    use crate::search::pattern_database::*;
+
+    // EL PUZLE DE OCHO: EL 0 ES EL HUECO
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    struct Tiles([u8; 9]);
+    impl State for Tiles {}
+
+    impl std::fmt::Display for Tiles {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+
+    const GOAL: Tiles = Tiles([1, 2, 3, 4, 5, 6, 7, 8, 0]);
+    // LAS FICHAS QUE EL PATRÓN NO GUARDA
+    const ANY: u8 = 9;
+
+    fn slides(tiles: &Tiles) -> Vec<(usize, Tiles)> {
+        let empty = tiles.0.iter().position(|t| *t == 0).unwrap();
+        let (row, col) = (empty / 3, empty % 3);
+        let mut neighbours = vec![];
+        if row > 0 {
+            neighbours.push(empty - 3);
+        }
+        if row < 2 {
+            neighbours.push(empty + 3);
+        }
+        if col > 0 {
+            neighbours.push(empty - 1);
+        }
+        if col < 2 {
+            neighbours.push(empty + 1);
+        }
+        neighbours
+            .into_iter()
+            .map(|from| {
+                let mut next = *tiles;
+                next.0.swap(empty, from);
+                (from, next)
+            })
+            .collect()
+    }
+
+    #[derive(Debug)]
+    struct EightPuzzle {}
+
+    impl SearchInfo<Tiles> for EightPuzzle {
+        // LA CASILLA DE LA FICHA QUE SE DESLIZA AL HUECO
+        type Action = usize;
+
+        fn expand_state(&self, tiles: &Tiles) -> Vec<(usize, Tiles)> {
+            slides(tiles)
+        }
+        fn is_goal(&self, tiles: &Tiles) -> bool {
+            *tiles == GOAL
+        }
+    }
+
+    // SOLO LAS FICHAS DEL GRUPO Y EL HUECO; MOVER LAS DEMÁS NO CUESTA
+    #[derive(Debug)]
+    struct TileGroup {
+        tiles: Vec<u8>,
+    }
+
+    impl Abstraction<Tiles> for TileGroup {
+        type Pattern = Tiles;
+
+        fn abstract_state(&self, tiles: &Tiles) -> Tiles {
+            let mut pattern = *tiles;
+            for tile in pattern.0.iter_mut() {
+                if *tile != 0 && !self.tiles.contains(tile) {
+                    *tile = ANY;
+                }
+            }
+            pattern
+        }
+
+        fn predecessors(&self, pattern: &Tiles) -> Vec<(u64, Tiles)> {
+            // DESLIZAR UNA FICHA SE DESHACE DESLIZÁNDOLA DE VUELTA
+            slides(pattern)
+                .into_iter()
+                .map(|(from, previous)| (if pattern.0[from] == ANY { 0 } else { 1 }, previous))
+                .collect()
+        }
+    }
+
+    fn exact_distances() -> HashMap<Tiles, u64> {
+        let mut distances = HashMap::new();
+        let mut not_processed = VecDeque::new();
+        distances.insert(GOAL, 0);
+        not_processed.push_back(GOAL);
+        while let Some(tiles) = not_processed.pop_front() {
+            let distance = distances[&tiles];
+            for (_, next) in slides(&tiles) {
+                if let Entry::Vacant(entry) = distances.entry(next) {
+                    entry.insert(distance + 1);
+                    not_processed.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+
+    fn group(tiles: &[u8]) -> PatternDatabase<Tiles, TileGroup> {
+        PatternDatabase::build(
+            TileGroup {
+                tiles: tiles.to_vec(),
+            },
+            &[GOAL],
+        )
+    }
+
+    #[test]
+    fn whole_pattern_is_exact() {
+        let exact = exact_distances();
+        // LA MITAD DE LAS PERMUTACIONES NO TIENE SOLUCIÓN
+        assert_eq!(exact.len(), 181440);
+        let all = group(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(all.len(), exact.len());
+        for (tiles, distance) in exact.iter() {
+            assert_eq!(all.distance(tiles), Some(*distance));
+        }
+        let unsolvable = Tiles([2, 1, 3, 4, 5, 6, 7, 8, 0]);
+        assert_eq!(all.distance(&unsolvable), None);
+        assert_eq!(all.heuristic(&unsolvable), 0);
+    }
+
+    #[test]
+    fn combined_patterns_are_admissible() {
+        let low = group(&[1, 2, 3, 4]);
+        let high = group(&[5, 6, 7, 8]);
+        let odd = group(&[1, 3, 5, 7]);
+        let even = group(&[2, 4, 6, 8]);
+        assert!(low.len() < 181440);
+
+        let rows = AdditiveHeuristic {
+            parts: vec![&low, &high],
+        };
+        let parity = AdditiveHeuristic {
+            parts: vec![&odd, &even],
+        };
+        let best = MaxHeuristic {
+            parts: vec![&rows, &parity],
+        };
+
+        let mut sum = 0;
+        for (tiles, distance) in exact_distances().iter() {
+            let value = best.heuristic(tiles);
+            assert!(value <= *distance);
+            sum += value;
+        }
+        assert!(sum > 0);
+        assert_eq!(best.heuristic(&GOAL), 0);
+    }
+
+    #[test]
+    fn search_with_pattern_databases() {
+        let low = group(&[1, 2, 3, 4]);
+        let high = group(&[5, 6, 7, 8]);
+        let rows = AdditiveHeuristic {
+            parts: vec![&low, &high],
+        };
+        let search = WithHeuristic {
+            delegate: EightPuzzle {},
+            heuristic: &rows,
+        };
+
+        // LA POSICIÓN MÁS LEJANA DEL OBJETIVO
+        let root = Tiles([8, 6, 7, 2, 5, 4, 3, 0, 1]);
+        let blind = a_star_search(root, &EightPuzzle {});
+        let result = a_star_search(root, &search);
+        println!(
+            "cost:{} blind:{} patterns:{}",
+            blind.cost, blind.stats.expanded, result.stats.expanded
+        );
+        assert_eq!(blind.cost, 31);
+        assert_eq!(result.cost, 31);
+        assert_eq!(*result.goal().unwrap(), GOAL);
+        assert!(result.stats.expanded * 10 < blind.stats.expanded);
+    }
+}
@@ -0,0 +1,470 @@
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+
+use crate::search::observer::*;
+use crate::search::*;
+
+const INFINITE: u64 = u64::MAX;
+
+struct SmaNode<T, A> {
+    state: T,
+    parent: Option<NodeId>,
+    action: Option<A>,
+    // POSICIÓN ENTRE LOS SUCESORES DEL PADRE
+    index: usize,
+    level: u64,
+    g: u64,
+    h: u64,
+    // ESTIMACIÓN DE f, ACTUALIZADA CON LA DE LOS HIJOS CUANDO YA SE HAN GENERADO TODOS
+    f: u64,
+    children: Vec<NodeId>,
+    // LOS HIJOS QUE SE HAN BORRADO PARA HACER SITIO: SU POSICIÓN Y SU f
+    forgotten: Vec<(usize, u64)>,
+    // CUÁNTOS SUCESORES TIENE, Y CUÁNTOS SE HAN GENERADO ALGUNA VEZ, EN ORDEN
+    successors: Option<usize>,
+    generated: usize,
+}
+
+impl<T, A> SmaNode<T, A> {
+    fn observed(&self) -> ObservedNode<'_, T> {
+        ObservedNode {
+            state: &self.state,
+            level: self.level,
+            g: self.g,
+            h: self.h,
+        }
+    }
+
+    fn is_expanded(&self) -> bool {
+        self.successors == Some(self.generated)
+    }
+
+    // LOS NODOS CON ALGÚN SUCESOR FUERA DE MEMORIA ESTÁN EN LA ABIERTA
+    fn is_open(&self) -> bool {
+        !(self.is_expanded() && self.generated > 0 && self.forgotten.is_empty())
+    }
+
+    // EL MEJOR VALOR QUE PUEDE TENER EL PRÓXIMO HIJO: UNO NUEVO NO BAJA DE LA f DEL NODO, Y
+    // UNO OLVIDADO TIENE LA f QUE TENÍA
+    fn open_f(&self) -> u64 {
+        if self.is_expanded() && self.generated > 0 {
+            self.forgotten.iter().map(|(_, f)| *f).min().unwrap()
+        } else {
+            self.f
+        }
+    }
+}
+
+// LA ABIERTA VA POR f Y, A IGUAL f, PRIMERO EL MÁS PROFUNDO. EL PEOR ES EL ÚLTIMO: EL DE
+// MAYOR f Y, A IGUAL f, EL MENOS PROFUNDO
+type OpenKey = (u64, Reverse<u64>, NodeId);
+
+struct Sma<'a, 'o, T: State, S: SearchInfo<T> + ?Sized> {
+    search_data: &'a S,
+    nodes: Vec<Option<SmaNode<T, S::Action>>>,
+    free: Vec<NodeId>,
+    in_memory: usize,
+    max_nodes: usize,
+    open: BTreeSet<OpenKey>,
+    depth_limited: bool,
+    stats: SearchStats,
+    best: Option<BestNode<T, S::Action>>,
+    observer: &'o mut dyn SearchObserver<T>,
+}
+
+impl<'a, 'o, T: State, S: SearchInfo<T> + ?Sized> Sma<'a, 'o, T, S> {
+    fn node(&self, id: NodeId) -> &SmaNode<T, S::Action> {
+        self.nodes[id].as_ref().unwrap()
+    }
+
+    fn key(&self, id: NodeId) -> OpenKey {
+        let node = self.node(id);
+        (node.open_f(), Reverse(node.level), id)
+    }
+
+    fn store(&mut self, node: SmaNode<T, S::Action>) -> NodeId {
+        self.in_memory += 1;
+        debug_assert!(self.in_memory <= self.max_nodes);
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.nodes[id] = Some(node);
+                id
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        self.open.insert(self.key(id));
+        id
+    }
+
+    // TODO CAMBIO EN UN NODO PASA POR AQUÍ PARA QUE LA ABIERTA SIGA AL DÍA
+    fn modify<F: FnOnce(&mut SmaNode<T, S::Action>)>(&mut self, id: NodeId, change: F) {
+        if self.node(id).is_open() {
+            self.open.remove(&self.key(id));
+        }
+        change(self.nodes[id].as_mut().unwrap());
+        if self.node(id).is_open() {
+            self.open.insert(self.key(id));
+        }
+    }
+
+    fn in_root_path(&self, id: NodeId, state: &T) -> bool {
+        let mut option = Some(id);
+        while let Some(current) = option {
+            if self.node(current).state == *state {
+                return true;
+            }
+            option = self.node(current).parent;
+        }
+        false
+    }
+
+    // LOS NODOS DESDE LA RAÍZ HASTA `id`, AMBOS INCLUIDOS
+    fn root_path(&self, id: NodeId) -> (Vec<T>, Vec<S::Action>) {
+        let mut states = Vec::new();
+        let mut actions = Vec::new();
+        let mut option = Some(id);
+        while let Some(current) = option {
+            let node = self.node(current);
+            states.push(node.state.clone());
+            actions.extend(node.action.clone());
+            option = node.parent;
+        }
+        states.reverse();
+        actions.reverse();
+        (states, actions)
+    }
+
+    fn keep_best(&mut self, id: NodeId) {
+        let node = self.node(id);
+        if let Some(b) = &self.best {
+            if (node.h, node.g) >= (b.h, b.g) {
+                return;
+            }
+        }
+        let (path, actions) = self.root_path(id);
+        let node = self.node(id);
+        self.best = Some(BestNode {
+            h: node.h,
+            g: node.g,
+            path,
+            actions,
+        });
+    }
+
+    // LOS SUCESORES SE VUELVEN A CALCULAR CADA VEZ, GUARDARLOS GASTARÍA LA MEMORIA QUE SE
+    // QUIERE AHORRAR. SE QUITAN LOS QUE VUELVEN A UN ESTADO DEL CAMINO
+    fn successors(&self, id: NodeId) -> Vec<(S::Action, T)> {
+        let mut successors = self.search_data.expand_state(&self.node(id).state);
+        successors.retain(|(_, state)| !self.in_root_path(id, state));
+        successors
+    }
+
+    // BORRA LA PEOR HOJA QUE NO SEA `keep`. SU f QUEDA EN EL PADRE, QUE VUELVE A LA ABIERTA
+    // PARA REGENERARLA CUANDO SEA EL MEJOR NODO
+    fn forget_worst_leaf(&mut self, keep: NodeId) {
+        let worst = self.open.iter().rev().map(|(_, _, id)| *id).find(|id| {
+            let node = self.node(*id);
+            *id != keep && node.parent.is_some() && node.children.is_empty()
+        });
+        // SI NO HAY NINGUNA, TODA LA MEMORIA ES EL CAMINO HASTA `keep`, Y ESE NODO NUNCA SE
+        // EXPANDE PORQUE SUS HIJOS NO CABRÍAN
+        let worst = worst.unwrap();
+        self.open.remove(&self.key(worst));
+        let node = self.nodes[worst].take().unwrap();
+        self.free.push(worst);
+        self.in_memory -= 1;
+        self.modify(node.parent.unwrap(), |parent| {
+            parent.children.retain(|c| *c != worst);
+            parent.forgotten.push((node.index, node.f));
+        });
+    }
+
+    // TRAS CAMBIAR LOS HIJOS DE `id`, SU f Y LA DE SUS ANTECESORES PASAN A SER LA MEJOR DE
+    // SUS HIJOS, LOS QUE ESTÁN EN MEMORIA Y LOS OLVIDADOS
+    fn back_up(&mut self, id: NodeId) {
+        let mut option = Some(id);
+        while let Some(current) = option {
+            let node = self.node(current);
+            if !node.is_expanded() {
+                return;
+            }
+            let f = node
+                .children
+                .iter()
+                .map(|c| self.node(*c).f)
+                .chain(node.forgotten.iter().map(|(_, f)| *f))
+                .min()
+                .unwrap_or(INFINITE);
+            if f == node.f {
+                return;
+            }
+            self.modify(current, |node| node.f = f);
+            option = self.node(current).parent;
+        }
+    }
+
+    fn dead_end(&mut self, id: NodeId) {
+        self.modify(id, |node| {
+            node.successors = Some(0);
+            node.f = INFINITE;
+        });
+        if let Some(parent) = self.node(id).parent {
+            self.back_up(parent);
+        }
+    }
+
+    // GENERA UN SUCESOR DE `id` QUE NO ESTÁ EN MEMORIA: EL SIGUIENTE QUE NO SE HA GENERADO
+    // NUNCA O, SI YA SE GENERARON TODOS, EL MEJOR DE LOS OLVIDADOS
+    fn generate_next(&mut self, id: NodeId) {
+        if self.in_memory >= self.max_nodes {
+            self.forget_worst_leaf(id);
+        }
+        let mut successors = self.successors(id);
+        let total = successors.len();
+        if total == 0 {
+            // SIN SUCESORES NO HAY CAMINO POR AQUÍ
+            self.dead_end(id);
+            return;
+        }
+
+        let node = self.node(id);
+        let (index, bound) = if node.generated < total {
+            (node.generated, node.f)
+        } else {
+            *node.forgotten.iter().min_by_key(|(_, f)| *f).unwrap()
+        };
+        let (action, state) = successors.swap_remove(index);
+        let level = node.level + 1;
+        let g = node.g + self.search_data.step_cost(&node.state, &state);
+        let h = self.search_data.heuristic(&state);
+        // UN NODO QUE NO ES OBJETIVO Y NO CABE CON SU CAMINO EN MEMORIA NO LLEVA A NINGUNA SOLUCIÓN
+        let f = if level + 1 >= self.max_nodes as u64 && !self.search_data.is_goal(&state) {
+            self.depth_limited = true;
+            INFINITE
+        } else {
+            bound.max(g + h)
+        };
+
+        let child = self.store(SmaNode {
+            state,
+            parent: Some(id),
+            action: Some(action),
+            index,
+            level,
+            g,
+            h,
+            f,
+            children: Vec::new(),
+            forgotten: Vec::new(),
+            successors: None,
+            generated: 0,
+        });
+        self.stats.generated(1);
+        self.observer
+            .generated(self.nodes[child].as_ref().unwrap().observed());
+
+        self.modify(id, |node| {
+            node.children.push(child);
+            node.forgotten.retain(|(i, _)| *i != index);
+            node.successors = Some(total);
+            node.generated = node.generated.max(index + 1);
+        });
+        self.back_up(id);
+    }
+}
+
+/// Simplified memory-bounded A* (SMA*): an A* that never keeps more than `max_nodes`
+/// nodes. Nodes are generated one at a time; when memory is full the leaf with the
+/// highest f is forgotten, its f is kept in the parent, and it is generated again if
+/// the parent becomes the best node. The solution is optimal whenever the optimal
+/// path fits in memory, that is, it has fewer than `max_nodes` states. States are
+/// only compared with the path from the root, so the same state can be in memory
+/// more than once. `DepthLimit` means that some path did not fit in memory or
+/// exceeded the `max_depth` of the domain.
+pub fn sma_star_search<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+    max_nodes: usize,
+) -> SearchResult<T, S::Action> {
+    sma_star_search_with(
+        root,
+        search_data,
+        max_nodes,
+        &mut NoObserver,
+        &SearchLimits::none(),
+    )
+}
+
+/// The closed set of the limits is the nodes in memory.
+pub fn sma_star_search_with<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+    max_nodes: usize,
+    observer: &mut dyn SearchObserver<T>,
+    limits: &SearchLimits,
+) -> SearchResult<T, S::Action> {
+    assert!(max_nodes >= 2, "Hacen falta al menos dos nodos en memoria");
+    let started = Instant::now();
+    let h = search_data.heuristic(&root);
+    let mut sma = Sma {
+        search_data,
+        nodes: Vec::new(),
+        free: Vec::new(),
+        in_memory: 0,
+        max_nodes,
+        open: BTreeSet::new(),
+        depth_limited: false,
+        stats: SearchStats::default(),
+        best: None,
+        observer,
+    };
+    let root = sma.store(SmaNode {
+        state: root,
+        parent: None,
+        action: None,
+        level: 0,
+        g: 0,
+        h,
+        index: 0,
+        f: h,
+        children: Vec::new(),
+        forgotten: Vec::new(),
+        successors: None,
+        generated: 0,
+    });
+    sma.stats.generated(1);
+    sma.observer
+        .generated(sma.nodes[root].as_ref().unwrap().observed());
+
+    while let Some(&(f, _, current)) = sma.open.iter().next() {
+        if f == INFINITE {
+            break;
+        }
+        if search_data.is_goal(&sma.node(current).state) {
+            sma.observer
+                .goal_found(sma.nodes[current].as_ref().unwrap().observed());
+            let cost = sma.node(current).g;
+            let (path, actions) = sma.root_path(current);
+            return SearchResult::from_path(Some((path, actions, cost)), false, sma.stats, started);
+        }
+
+        if let Some(max) = search_data.max_depth() {
+            if sma.node(current).level >= max {
+                sma.depth_limited = true;
+                sma.observer
+                    .pruned_by_depth(sma.nodes[current].as_ref().unwrap().observed());
+                sma.dead_end(current);
+                continue;
+            }
+        }
+
+        sma.keep_best(current);
+        if limits.exceeded(&sma.stats, sma.in_memory, started) {
+            return SearchResult::budget_exceeded(sma.best, sma.stats, started);
+        }
+
+        sma.stats.expanded(sma.node(current).level);
+        sma.observer
+            .expanded(sma.nodes[current].as_ref().unwrap().observed());
+        sma.generate_next(current);
+        sma.stats.frontier(sma.open.len());
+        report_progress(sma.observer, &mut sma.stats, started);
+    }
+
+    SearchResult::from_path(None, sma.depth_limited, sma.stats, started)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crossteaser::crossteaser_search::*;
+    use crate::search::astar::tests::*;
+    use crate::search::astar::*;
+    use crate::search::sma::*;
+    use crate::search::tests::NoGoal;
+
+    #[test]
+    fn sma_in_terrain() {
+        let search = SearchInTerrain {
+            terrain: vec![
+                vec![1, 1, 1, 1, 1],
+                vec![1, 9, 9, 9, 1],
+                vec![1, 9, 1, 9, 1],
+                vec![1, 9, 1, 9, 1],
+                vec![1, 1, 1, 1, 1],
+            ],
+            goal: Vector(2, 2),
+        };
+        let optimal = a_star_search(Vector(0, 0), &search);
+        assert_eq!(optimal.path.len(), 9);
+        for max_nodes in [9, 10, 50, 1000].iter() {
+            let result = sma_star_search(Vector(0, 0), &search, *max_nodes);
+            assert_eq!(result.cost, optimal.cost);
+            assert_eq!(result.path, optimal.path);
+        }
+        // EL CAMINO ÓPTIMO NO CABE: SE ENCUENTRA EL MEJOR DE LOS QUE CABEN
+        let result = sma_star_search(Vector(0, 0), &search, 6);
+        assert_eq!(result.path.len(), 5);
+        assert_eq!(result.cost, 12);
+    }
+
+    #[test]
+    fn sma_path_does_not_fit() {
+        let search = SearchToGoal { goal: Vector(3, 4) };
+        // SIETE MOVIMIENTOS SON OCHO ESTADOS
+        let result = sma_star_search(Vector(0, 0), &search, 8);
+        assert_eq!(result.cost, 7);
+        let result = sma_star_search(Vector(0, 0), &search, 7);
+        assert_eq!(result.outcome, SearchOutcome::DepthLimit);
+    }
+
+    #[test]
+    fn sma_without_goal() {
+        let result = sma_star_search(vec![], &NoGoal {}, 400);
+        assert_eq!(result.outcome, SearchOutcome::Exhausted);
+        assert_eq!(result.stats.generated, 1 + 4 + 16 + 64 + 256);
+
+        // SIN SITIO PARA TODO EL ÁRBOL HAY QUE VOLVER A GENERAR LO OLVIDADO
+        let result = sma_star_search(vec![], &NoGoal {}, 100);
+        assert_eq!(result.outcome, SearchOutcome::Exhausted);
+        assert!(result.stats.generated > 1 + 4 + 16 + 64 + 256);
+    }
+
+    #[test]
+    fn sma_optimal_on_scrambled_boards() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let search = HalfOfDifferences { goal: board };
+
+        for step in (10..=40).step_by(5) {
+            let scrambled = scrambled_board(&board, step);
+            let optimal = a_star_search(scrambled, &search);
+            let result = sma_star_search(scrambled, &search, 200);
+            println!(
+                "step:{} a*:{} {} sma:{} {}",
+                step, optimal.cost, optimal.stats.expanded, result.cost, result.stats.expanded
+            );
+            assert_eq!(result.cost, optimal.cost);
+            assert_eq!(
+                scrambled
+                    .apply_moves_to_empty_position_get_last(&result.actions)
+                    .unwrap(),
+                board
+            );
+        }
+    }
+
+    #[test]
+    fn sma_stops_at_limits() {
+        let search = SearchToGoal { goal: Vector(0, 0) };
+        let limits = SearchLimits {
+            max_expanded: Some(20),
+            ..SearchLimits::none()
+        };
+        let result = sma_star_search_with(Vector(1, 1), &search, 10, &mut NoObserver, &limits);
+        assert_eq!(result.outcome, SearchOutcome::BudgetExceeded);
+        assert_eq!(result.stats.expanded, 20);
+    }
+}
@@ -0,0 +1,303 @@
+use crate::search::open_list::*;
+use crate::search::*;
+
+/// One path from the root to a goal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution<T, A> {
+    pub path: Vec<T>,
+    pub actions: Vec<A>,
+    pub cost: u64,
+}
+
+/// The paths from the root to a goal, cheapest first, found one at a time. See
+/// `shortest_paths`.
+pub struct ShortestPaths<'a, T: State, S: SearchInfo<T> + ?Sized> {
+    tree: SearchTree<'a, T, S>,
+    // LA CLAVE ES EL NODO: EL MISMO ESTADO PUEDE ESTAR ABIERTO POR VARIOS CAMINOS
+    open: OpenList<NodeId, ()>,
+    limits: SearchLimits,
+    // LOS NODOS CON f MAYOR NO SE GUARDAN
+    max_cost: Option<u64>,
+    stats: SearchStats,
+    started: Instant,
+    stopped: bool,
+}
+
+impl<'a, T: State, S: SearchInfo<T> + ?Sized> ShortestPaths<'a, T, S> {
+    pub fn stats(&self) -> &SearchStats {
+        &self.stats
+    }
+
+    /// Whether the iterator ended because the limits were exceeded, so there may be
+    /// more solutions.
+    pub fn stopped(&self) -> bool {
+        self.stopped
+    }
+}
+
+impl<'a, T: State, S: SearchInfo<T> + ?Sized> Iterator for ShortestPaths<'a, T, S> {
+    type Item = Solution<T, S::Action>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let search_data = self.tree.search();
+        loop {
+            // SE MIRA ANTES DE SACAR EL NODO: UNA VEZ PARADO NO SE PIERDE NI SE DEVUELVE NADA MÁS
+            if self.stopped {
+                return None;
+            }
+            if self
+                .limits
+                .exceeded(&self.stats, self.tree.len(), self.started)
+            {
+                self.stopped = true;
+                break;
+            }
+            let (current, ()) = match self.open.pop() {
+                Some(popped) => popped,
+                None => break,
+            };
+            // EL OBJETIVO SE COMPRUEBA AL SACARLO: CON UNA HEURÍSTICA ADMISIBLE NINGÚN NODO
+            // ABIERTO LLEVA A UN CAMINO MÁS BARATO
+            if search_data.is_goal(&self.tree[current].state) {
+                self.stats.elapsed = self.started.elapsed();
+                return Some(Solution {
+                    path: self.tree.root_path_state(current),
+                    actions: self.tree.root_path_actions(current),
+                    cost: self.tree[current].g(),
+                });
+            }
+
+            if let Some(max) = search_data.max_depth() {
+                if self.tree[current].level() >= max {
+                    continue;
+                }
+            }
+
+            let children = self.tree.expand(current);
+            self.stats.expanded(self.tree[current].level());
+            self.stats.generated(children.len());
+            for child in children {
+                // SOLO CAMINOS SIN CICLOS, SI NO HABRÍA INFINITOS
+                if self.tree.in_root_path(current, &child.state) {
+                    continue;
+                }
+                let (f, g) = (child.f(), child.g());
+                if self.max_cost.is_some_and(|max| f > max) {
+                    continue;
+                }
+                let id = self.tree.push(child);
+                self.open.push(id, f, g, ());
+            }
+            self.stats.frontier(self.open.len());
+        }
+        self.stats.elapsed = self.started.elapsed();
+        None
+    }
+}
+
+// PARADO O SIN NODOS ABIERTOS, next SIGUE DEVOLVIENDO None
+impl<'a, T: State, S: SearchInfo<T> + ?Sized> std::iter::FusedIterator for ShortestPaths<'a, T, S> {}
+
+/// Every path without cycles from `root` to a goal, in order of cost, computed lazily:
+/// `take(k)` gives the k shortest paths and `find` the shortest one with some property.
+/// It is an A* without closed set, so a state reached by several paths is expanded once
+/// for each of them. The order is right when the heuristic is admissible. A path ends
+/// at the first goal it reaches.
+pub fn shortest_paths<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+) -> ShortestPaths<'_, T, S> {
+    shortest_paths_with(root, search_data, SearchLimits::none())
+}
+
+/// The closed set of the limits is every node kept, in the open list or in the paths to
+/// it. When they are exceeded the iterator ends and `stopped` returns true.
+pub fn shortest_paths_with<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+    limits: SearchLimits,
+) -> ShortestPaths<'_, T, S> {
+    let mut tree = SearchTree::new(search_data);
+    let root = tree.add_root(root);
+    let mut open = OpenList::new(search_data.tie_break());
+    open.push(root, tree[root].f(), 0, ());
+    let mut stats = SearchStats::default();
+    stats.generated(1);
+    ShortestPaths {
+        tree,
+        open,
+        limits,
+        max_cost: None,
+        stats,
+        started: Instant::now(),
+        stopped: false,
+    }
+}
+
+/// The solutions of optimal cost, one at a time. See `all_optimal_solutions`.
+pub struct OptimalSolutions<'a, T: State, S: SearchInfo<T> + ?Sized> {
+    paths: ShortestPaths<'a, T, S>,
+}
+
+impl<'a, T: State, S: SearchInfo<T> + ?Sized> OptimalSolutions<'a, T, S> {
+    pub fn stats(&self) -> &SearchStats {
+        self.paths.stats()
+    }
+}
+
+impl<'a, T: State, S: SearchInfo<T> + ?Sized> Iterator for OptimalSolutions<'a, T, S> {
+    type Item = Solution<T, S::Action>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // EL PRIMER CAMINO FIJA EL COSTE ÓPTIMO, Y A PARTIR DE AHÍ NO SE GUARDA NADA MÁS CARO
+        let solution = self.paths.next()?;
+        if *self.paths.max_cost.get_or_insert(solution.cost) < solution.cost {
+            return None;
+        }
+        Some(solution)
+    }
+}
+
+/// Every solution of optimal cost, from `shortest_paths`, to count them or to choose
+/// among them. Once the first one is found the nodes with a higher f are dropped, so
+/// the iterator ends even if there is no costlier solution.
+pub fn all_optimal_solutions<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+) -> OptimalSolutions<'_, T, S> {
+    OptimalSolutions {
+        paths: shortest_paths(root, search_data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crossteaser::crossteaser_search::*;
+    use crate::search::astar::tests::*;
+    use crate::search::astar::*;
+    use crate::search::solutions::*;
+    use crate::search::tests::DummySearch;
+
+    // EL ÁRBOL DE DummySearch, CON OBJETIVO EN TODOS LOS ESTADOS CUYOS NÚMEROS SUMAN TRES
+    #[derive(Debug)]
+    struct SumIsThree {
+        exact_length: Option<usize>,
+    }
+
+    impl SearchInfo<Vec<i32>> for SumIsThree {
+        type Action = i32;
+
+        fn expand_state(&self, state: &Vec<i32>) -> Vec<(i32, Vec<i32>)> {
+            DummySearch {}.expand_state(state)
+        }
+        fn is_goal(&self, state: &Vec<i32>) -> bool {
+            state.iter().sum::<i32>() == 3
+                && self.exact_length.is_none_or(|length| state.len() == length)
+        }
+    }
+
+    #[test]
+    fn one_solution_in_dummy_search() {
+        let solutions: Vec<_> = shortest_paths(vec![], &DummySearch {}).collect();
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0].actions, vec![0, 1, 2, 3]);
+        assert_eq!(solutions[0].cost, 4);
+    }
+
+    #[test]
+    fn shortest_paths_in_order() {
+        let search = SumIsThree { exact_length: None };
+        let solutions: Vec<_> = shortest_paths(vec![], &search).collect();
+        // LOS CAMINOS ACABAN EN EL PRIMER OBJETIVO: [3], LUEGO [0,3] [1,2] [2,1]...
+        let mut by_cost = vec![0; 5];
+        for solution in solutions.iter() {
+            by_cost[solution.cost as usize] += 1;
+            assert_eq!(solution.actions.len(), solution.cost as usize);
+            assert_eq!(solution.path.last().unwrap(), &solution.actions);
+        }
+        assert_eq!(by_cost, vec![0, 1, 3, 6, 10]);
+        assert!(solutions.windows(2).all(|s| s[0].cost <= s[1].cost));
+
+        let first_three: Vec<u64> = shortest_paths(vec![], &search)
+            .take(3)
+            .map(|s| s.cost)
+            .collect();
+        assert_eq!(first_three, vec![1, 2, 2]);
+    }
+
+    #[test]
+    fn all_optimal_in_dummy_search() {
+        // LAS COMPOSICIONES DE 3 EN CUATRO NÚMEROS DE 0 A 3
+        let search = SumIsThree {
+            exact_length: Some(4),
+        };
+        let solutions: Vec<_> = all_optimal_solutions(vec![], &search).collect();
+        assert_eq!(solutions.len(), 20);
+        assert!(solutions.iter().all(|s| s.cost == 4));
+
+        let search = SumIsThree { exact_length: None };
+        assert_eq!(all_optimal_solutions(vec![], &search).count(), 1);
+    }
+
+    #[test]
+    fn shortest_paths_in_grid() {
+        // TRES PASOS AL ESTE Y CUATRO AL SUR EN CUALQUIER ORDEN
+        let search = SearchToGoal { goal: Vector(3, 4) };
+        assert_eq!(all_optimal_solutions(Vector(0, 0), &search).count(), 35);
+
+        // EL MÁS CORTO QUE NO PASA POR (1, 1)
+        let solution = shortest_paths(Vector(0, 0), &search)
+            .find(|s| !s.path.contains(&Vector(1, 1)))
+            .unwrap();
+        assert_eq!(solution.cost, 7);
+        assert!(!solution.path.contains(&Vector(1, 1)));
+    }
+
+    #[test]
+    fn shortest_paths_stop_at_limits() {
+        let search = SumIsThree {
+            exact_length: Some(4),
+        };
+        let limits = SearchLimits {
+            max_expanded: Some(10),
+            ..SearchLimits::none()
+        };
+        let mut paths = shortest_paths_with(vec![], &search, limits);
+        assert_eq!(paths.next(), None);
+        assert!(paths.stopped());
+        assert_eq!(paths.stats().expanded, 10);
+        // PARADO, NO SE SACA NINGÚN NODO MÁS
+        let open = paths.open.len();
+        assert_eq!(paths.next(), None);
+        assert_eq!(paths.next(), None);
+        assert_eq!(paths.open.len(), open);
+        assert_eq!(paths.stats().expanded, 10);
+    }
+
+    #[test]
+    fn all_optimal_on_scrambled_board() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let search = HalfOfDifferences { goal: board };
+        let scrambled = scrambled_board(&board, 20);
+        let optimal = a_star_search(scrambled, &search);
+
+        let solutions: Vec<_> = all_optimal_solutions(scrambled, &search).collect();
+        println!("cost:{} solutions:{}", optimal.cost, solutions.len());
+        assert!(!solutions.is_empty());
+        for solution in solutions.iter() {
+            assert_eq!(solution.cost, optimal.cost);
+            assert_eq!(
+                scrambled
+                    .apply_moves_to_empty_position_get_last(&solution.actions)
+                    .unwrap(),
+                board
+            );
+        }
+        for (i, solution) in solutions.iter().enumerate() {
+            assert!(solutions[i + 1..]
+                .iter()
+                .all(|other| other.actions != solution.actions));
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+
+use crate::search::open_list::*;
+use crate::search::*;
+
+/// The search run by a `Stepper`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// A*, reopening the nodes reached later by a cheaper path.
+    AStar,
+    /// Breadth first graph search: a state already generated is not queued again.
+    BreadthFirst,
+    /// Depth first search skipping the states already in the path of each node.
+    DepthFirst,
+}
+
+/// What one call to `Stepper::step` did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// The node was expanded and `added` of its children went into the frontier.
+    Expanded { node: NodeId, added: usize },
+    /// The node was at `max_depth` and was not expanded.
+    PrunedByDepth(NodeId),
+    /// The node taken from the frontier is a goal. The search is over.
+    GoalFound(NodeId),
+    /// The frontier is empty: the outcome is `Exhausted` or `DepthLimit`.
+    Finished(SearchOutcome),
+}
+
+enum Frontier<T: State> {
+    Open(OpenList<T, NodeId>),
+    Queue(VecDeque<NodeId>),
+    Stack(Vec<NodeId>),
+}
+
+/// A search that expands one node each time `step` is called, so it can be drawn
+/// frame by frame or interleaved with other searches. Between steps the frontier, the
+/// closed set and the best solution so far can be looked at. As an iterator it returns
+/// every step up to and including the last one.
+pub struct Stepper<'a, T: State, S: SearchInfo<T> + ?Sized> {
+    strategy: Strategy,
+    tree: SearchTree<'a, T, S>,
+    frontier: Frontier<T>,
+    // LOS ESTADOS EXPANDIDOS CON SU NODO. SOLO A* LO USA PARA DESCARTAR NODOS
+    closed: HashMap<T, NodeId>,
+    generated: HashSet<T>,
+    depth_limited: bool,
+    goal: Option<NodeId>,
+    finished: bool,
+    best: Option<NodeId>,
+    stats: SearchStats,
+    started: Instant,
+}
+
+impl<'a, T: State, S: SearchInfo<T> + ?Sized> Stepper<'a, T, S> {
+    pub fn new(root: T, search_data: &'a S, strategy: Strategy) -> Self {
+        let mut tree = SearchTree::new(search_data);
+        let mut generated = HashSet::new();
+        if strategy == Strategy::BreadthFirst {
+            generated.insert(root.clone());
+        }
+        let root = tree.add_root(root);
+        let frontier = match strategy {
+            Strategy::AStar => {
+                let mut open = OpenList::new(search_data.tie_break());
+                open.push(tree[root].state.clone(), tree[root].f(), 0, root);
+                Frontier::Open(open)
+            }
+            Strategy::BreadthFirst => Frontier::Queue(VecDeque::from(vec![root])),
+            Strategy::DepthFirst => Frontier::Stack(vec![root]),
+        };
+        let mut stats = SearchStats::default();
+        stats.generated(1);
+        Stepper {
+            strategy,
+            tree,
+            frontier,
+            closed: HashMap::new(),
+            generated,
+            depth_limited: false,
+            goal: None,
+            finished: false,
+            best: None,
+            stats,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    /// Every node created so far, to look up the ids returned by the steps.
+    pub fn tree(&self) -> &SearchTree<'a, T, S> {
+        &self.tree
+    }
+
+    pub fn stats(&self) -> &SearchStats {
+        &self.stats
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// The nodes waiting to be expanded. The stack of the depth first search is listed
+    /// from the bottom, so the next node is the last one.
+    pub fn frontier(&self) -> Vec<NodeId> {
+        match &self.frontier {
+            Frontier::Open(open) => open.iter().map(|(_, id)| *id).collect(),
+            Frontier::Queue(queue) => queue.iter().copied().collect(),
+            Frontier::Stack(stack) => stack.clone(),
+        }
+    }
+
+    /// The expanded states, each one with the last node that expanded it.
+    pub fn closed(&self) -> &HashMap<T, NodeId> {
+        &self.closed
+    }
+
+    /// The result if the search stopped now: the solution if a goal was found, the
+    /// outcome if the frontier is empty, and otherwise `BudgetExceeded` with the path to
+    /// the expanded node with the lowest heuristic.
+    pub fn result(&self) -> SearchResult<T, S::Action> {
+        let stats = self.stats.clone();
+        if self.goal.is_some() || self.finished {
+            return SearchResult::from_node(
+                &self.tree,
+                self.goal,
+                self.depth_limited,
+                stats,
+                self.started,
+            );
+        }
+        let best = self.best.map(|id| BestNode {
+            h: self.tree[id].h(),
+            g: self.tree[id].g(),
+            path: self.tree.root_path_state(id),
+            actions: self.tree.root_path_actions(id),
+        });
+        SearchResult::budget_exceeded(best, stats, self.started)
+    }
+
+    fn pop(&mut self) -> Option<NodeId> {
+        match &mut self.frontier {
+            Frontier::Open(open) => open.pop().map(|(_, id)| id),
+            Frontier::Queue(queue) => queue.pop_front(),
+            Frontier::Stack(stack) => stack.pop(),
+        }
+    }
+
+    fn frontier_len(&self) -> usize {
+        match &self.frontier {
+            Frontier::Open(open) => open.len(),
+            Frontier::Queue(queue) => queue.len(),
+            Frontier::Stack(stack) => stack.len(),
+        }
+    }
+
+    /// Takes the next node from the frontier and expands it. Once the search is over it
+    /// keeps returning its last step.
+    pub fn step(&mut self) -> Step {
+        if let Some(goal) = self.goal {
+            return Step::GoalFound(goal);
+        }
+        let current = match self.pop() {
+            Some(current) => current,
+            None => {
+                self.finished = true;
+                self.stats.elapsed = self.started.elapsed();
+                let outcome = if self.depth_limited {
+                    SearchOutcome::DepthLimit
+                } else {
+                    SearchOutcome::Exhausted
+                };
+                return Step::Finished(outcome);
+            }
+        };
+        let search_data = self.tree.search();
+        let level = self.tree[current].level();
+
+        if search_data.is_goal(&self.tree[current].state) {
+            self.goal = Some(current);
+            self.finished = true;
+            self.stats.elapsed = self.started.elapsed();
+            return Step::GoalFound(current);
+        }
+
+        if let Some(max) = search_data.max_depth() {
+            if level >= max {
+                self.depth_limited = true;
+                return Step::PrunedByDepth(current);
+            }
+        }
+
+        let better = self.best.is_none_or(|best| {
+            (self.tree[current].h(), self.tree[current].g())
+                < (self.tree[best].h(), self.tree[best].g())
+        });
+        if better {
+            self.best = Some(current);
+        }
+
+        let children = self.tree.expand(current);
+        self.stats.expanded(level);
+        self.stats.generated(children.len());
+        self.closed
+            .insert(self.tree[current].state.clone(), current);
+        let added = match self.strategy {
+            Strategy::AStar => self.add_a_star(children),
+            Strategy::BreadthFirst => self.add_breadth_first(children),
+            Strategy::DepthFirst => self.add_depth_first(current, children),
+        };
+        self.stats.frontier(self.frontier_len());
+        self.stats.elapsed = self.started.elapsed();
+        Step::Expanded {
+            node: current,
+            added,
+        }
+    }
+
+    // COMO EN a_star_search: SE QUEDA LA COPIA MÁS BARATA DE CADA ESTADO, Y LOS EXPANDIDOS
+    // A LOS QUE SE LLEGA POR UN CAMINO MEJOR SE VUELVEN A ABRIR
+    fn add_a_star(&mut self, children: Vec<SearchNode<T, S::Action>>) -> usize {
+        let open = match &mut self.frontier {
+            Frontier::Open(open) => open,
+            _ => unreachable!(),
+        };
+        let mut added = 0;
+        for child in children {
+            let (f, g) = (child.f(), child.g());
+            if let Some(&closed) = self.closed.get(&child.state) {
+                if self.tree[closed].g() <= g {
+                    continue;
+                }
+                self.closed.remove(&child.state);
+                self.tree.reparent(closed, &child);
+                open.push(child.state, f, g, closed);
+                added += 1;
+                continue;
+            }
+            if let Some(&id) = open.get(&child.state) {
+                if open.decrease_key(&child.state, f, g, id) {
+                    self.tree.reparent(id, &child);
+                }
+                continue;
+            }
+            let state = child.state.clone();
+            let id = self.tree.push(child);
+            open.push(state, f, g, id);
+            added += 1;
+        }
+        added
+    }
+
+    fn add_breadth_first(&mut self, children: Vec<SearchNode<T, S::Action>>) -> usize {
+        let queue = match &mut self.frontier {
+            Frontier::Queue(queue) => queue,
+            _ => unreachable!(),
+        };
+        let mut added = 0;
+        for child in children {
+            if !self.generated.insert(child.state.clone()) {
+                continue;
+            }
+            queue.push_back(self.tree.push(child));
+            added += 1;
+        }
+        added
+    }
+
+    // LOS HIJOS SE APILAN AL REVÉS PARA QUE EL PRIMERO SEA EL PRIMERO EN EXPANDIRSE
+    fn add_depth_first(
+        &mut self,
+        current: NodeId,
+        children: Vec<SearchNode<T, S::Action>>,
+    ) -> usize {
+        let stack = match &mut self.frontier {
+            Frontier::Stack(stack) => stack,
+            _ => unreachable!(),
+        };
+        let mut added = 0;
+        for child in children.into_iter().rev() {
+            if self.tree.in_root_path(current, &child.state) {
+                continue;
+            }
+            stack.push(self.tree.push(child));
+            added += 1;
+        }
+        added
+    }
+}
+
+impl<'a, T: State, S: SearchInfo<T> + ?Sized> Iterator for Stepper<'a, T, S> {
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Step> {
+        if self.finished {
+            return None;
+        }
+        Some(self.step())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crossteaser::crossteaser_search::*;
+    use crate::search::astar::tests::*;
+    use crate::search::astar::*;
+    use crate::search::stepper::*;
+    use crate::search::tests::{DummySearch, NoGoal};
+
+    fn run<T: State, S: SearchInfo<T>>(stepper: &mut Stepper<T, S>) -> Step {
+        stepper.last().unwrap()
+    }
+
+    #[test]
+    fn a_star_steps_like_a_star() {
+        let search = SearchInTerrain {
+            terrain: vec![
+                vec![1, 1, 1, 1, 1],
+                vec![1, 9, 9, 9, 1],
+                vec![1, 9, 1, 9, 1],
+                vec![1, 9, 1, 9, 1],
+                vec![1, 1, 1, 1, 1],
+            ],
+            goal: Vector(2, 2),
+        };
+        let expected = a_star_search(Vector(0, 0), &search);
+        let mut stepper = Stepper::new(Vector(0, 0), &search, Strategy::AStar);
+        let last = run(&mut stepper);
+        assert!(matches!(last, Step::GoalFound(_)));
+        let result = stepper.result();
+        assert_eq!(result.path, expected.path);
+        assert_eq!(result.cost, expected.cost);
+        assert_eq!(result.stats.expanded, expected.stats.expanded);
+    }
+
+    #[test]
+    fn breadth_first_steps_like_breadth_first() {
+        let mut stepper = Stepper::new(vec![], &DummySearch {}, Strategy::BreadthFirst);
+        assert_eq!(stepper.step(), Step::Expanded { node: 0, added: 4 });
+        assert_eq!(stepper.frontier().len(), 4);
+        assert_eq!(stepper.closed().len(), 1);
+        assert!(stepper.closed().contains_key(&vec![]));
+
+        run(&mut stepper);
+        let result = stepper.result();
+        assert_eq!(result.actions, vec![0, 1, 2, 3]);
+        assert_eq!(result.stats.expanded, 1 + 4 + 16 + 64 + 27);
+    }
+
+    #[test]
+    fn depth_first_goes_down_first() {
+        let mut stepper = Stepper::new(vec![], &DummySearch {}, Strategy::DepthFirst);
+        let expanded: Vec<Vec<i32>> = (0..4)
+            .map(|_| match stepper.step() {
+                Step::Expanded { node, .. } => stepper.tree()[node].state.clone(),
+                step => panic!("{:?}", step),
+            })
+            .collect();
+        assert_eq!(expanded, vec![vec![], vec![0], vec![0, 0], vec![0, 0, 0]]);
+        // EL SIGUIENTE ES UNA HOJA: SE EXPANDE SIN AÑADIR NADA
+        assert_eq!(stepper.frontier().len(), 3 * 3 + 4);
+        assert!(matches!(stepper.step(), Step::Expanded { added: 0, .. }));
+
+        run(&mut stepper);
+        assert_eq!(stepper.result().actions, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn stepper_without_goal() {
+        let mut stepper = Stepper::new(vec![], &NoGoal {}, Strategy::BreadthFirst);
+        assert_eq!(stepper.by_ref().count(), 1 + 4 + 16 + 64 + 256 + 1);
+        assert!(stepper.is_finished());
+        assert_eq!(stepper.step(), Step::Finished(SearchOutcome::Exhausted));
+        assert_eq!(stepper.result().outcome, SearchOutcome::Exhausted);
+    }
+
+    #[test]
+    fn stepper_respects_max_depth() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let search = BoardSearchWithGoal {
+            goal: board,
+            max_depth: Some(3),
+        };
+        let scrambled = scrambled_board(&board, 20);
+        for strategy in [
+            Strategy::AStar,
+            Strategy::BreadthFirst,
+            Strategy::DepthFirst,
+        ]
+        .iter()
+        {
+            let mut stepper = Stepper::new(scrambled, &search, *strategy);
+            assert_eq!(run(&mut stepper), Step::Finished(SearchOutcome::DepthLimit));
+        }
+    }
+
+    #[test]
+    fn interleaved_searches_on_boards() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let search = HalfOfDifferences { goal: board };
+        let scrambled = scrambled_board(&board, 20);
+        let optimal = a_star_search(scrambled, &search);
+
+        let mut a_star = Stepper::new(scrambled, &search, Strategy::AStar);
+        let mut breadth_first = Stepper::new(scrambled, &search, Strategy::BreadthFirst);
+
+        // MIENTRAS NO HAY SOLUCIÓN, EL RESULTADO ES EL MEJOR CAMINO HASTA AHORA
+        a_star.step();
+        let partial = a_star.result();
+        assert_eq!(partial.outcome, SearchOutcome::BudgetExceeded);
+        assert_eq!(partial.path, vec![scrambled]);
+
+        // UN PASO DE CADA UNA HASTA QUE TERMINAN LAS DOS
+        let mut steps = 1;
+        while !a_star.is_finished() || !breadth_first.is_finished() {
+            a_star.next();
+            breadth_first.next();
+            steps += 1;
+        }
+        println!(
+            "steps:{} a*:{} bfs:{}",
+            steps,
+            a_star.stats().expanded,
+            breadth_first.stats().expanded
+        );
+        for stepper in [&a_star, &breadth_first].iter() {
+            let result = stepper.result();
+            assert!(result.is_found());
+            assert_eq!(result.cost, optimal.cost);
+        }
+        assert!(a_star.stats().expanded < breadth_first.stats().expanded);
+    }
+}
@@ -0,0 +1,367 @@
+use std::ops::Index;
+
+use crate::search::*;
+
+/// Position of a node in its `SearchTree`.
+pub type NodeId = usize;
+
+/// Every node created by a search, stored contiguously. A node points to its parent
+/// by index, so paths are rebuilt walking the indices back to the root. The tree is
+/// generic over the search so the calls to the domain are not virtual.
+#[derive(Debug)]
+pub struct SearchTree<'a, T: State, S: SearchInfo<T> + ?Sized> {
+    nodes: Vec<SearchNode<T, S::Action>>,
+    search: &'a S,
+}
+
+impl<'a, T: State, S: SearchInfo<T> + ?Sized> SearchTree<'a, T, S> {
+    pub fn new(search: &'a S) -> Self {
+        SearchTree {
+            nodes: Vec::new(),
+            search,
+        }
+    }
+
+    pub fn search(&self) -> &'a S {
+        self.search
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn add_root(&mut self, state: T) -> NodeId {
+        let root = SearchNode {
+            parent: None,
+            action: None,
+            level: 0,
+            cost: 0,
+            heuristic: self.search.heuristic(&state),
+            cached_state_hash: simple_hash(&state),
+            state,
+        };
+        self.push(root)
+    }
+
+    /// A child of `parent`, not stored in the tree until it is pushed.
+    pub fn child(&self, parent: NodeId, action: S::Action, state: T) -> SearchNode<T, S::Action> {
+        let node = &self.nodes[parent];
+        SearchNode {
+            parent: Some(parent),
+            action: Some(action),
+            level: node.level + 1,
+            cost: node.cost + self.search.step_cost(&node.state, &state),
+            heuristic: self.search.heuristic(&state),
+            cached_state_hash: simple_hash(&state),
+            state,
+        }
+    }
+
+    /// The children of a node, not stored in the tree.
+    pub fn expand(&self, id: NodeId) -> Vec<SearchNode<T, S::Action>> {
+        self.search
+            .expand_state(&self.nodes[id].state)
+            .into_iter()
+            .map(|(action, state)| self.child(id, action, state))
+            .collect()
+    }
+
+    pub fn push(&mut self, node: SearchNode<T, S::Action>) -> NodeId {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// Expands a node and stores all its children.
+    pub fn expand_and_push(&mut self, id: NodeId) -> Vec<NodeId> {
+        self.expand(id)
+            .into_iter()
+            .map(|child| self.push(child))
+            .collect()
+    }
+
+    /// Moves a node under a new parent, taking the action, level and cost of `from`.
+    pub(crate) fn reparent(&mut self, id: NodeId, from: &SearchNode<T, S::Action>) {
+        let node = &mut self.nodes[id];
+        node.parent = from.parent;
+        node.action = from.action.clone();
+        node.level = from.level;
+        node.cost = from.cost;
+    }
+
+    /// Forgets the nodes from `len` on. The depth first algorithms use it to drop the
+    /// subtrees they have already explored.
+    pub fn truncate(&mut self, len: usize) {
+        self.nodes.truncate(len);
+    }
+
+    /// Nodes from `id` to the root, both included.
+    pub fn root_path(&self, id: NodeId) -> Vec<NodeId> {
+        let mut ret = vec![id];
+        let mut current = id;
+        while let Some(parent) = self.nodes[current].parent {
+            ret.push(parent);
+            current = parent;
+        }
+        ret
+    }
+
+    /// States from the root to `id`, both included.
+    pub fn root_path_state(&self, id: NodeId) -> Vec<T> {
+        let mut ret: Vec<T> = self
+            .root_path(id)
+            .iter()
+            .map(|n| self.nodes[*n].state.clone())
+            .collect();
+        ret.reverse();
+        ret
+    }
+
+    /// Actions from the root to `id`: one less than states.
+    pub fn root_path_actions(&self, id: NodeId) -> Vec<S::Action> {
+        let mut ret: Vec<S::Action> = self
+            .root_path(id)
+            .iter()
+            .filter_map(|n| self.nodes[*n].action.clone())
+            .collect();
+        ret.reverse();
+        ret
+    }
+
+    /// Whether `state` is in the path from `id` to the root.
+    pub fn in_root_path(&self, id: NodeId, state: &T) -> bool {
+        let mut option = Some(id);
+        while let Some(current) = option {
+            if self.nodes[current].state == *state {
+                return true;
+            }
+            option = self.nodes[current].parent;
+        }
+        false
+    }
+}
+
+impl<'a, T: State, S: SearchInfo<T> + ?Sized> Index<NodeId> for SearchTree<'a, T, S> {
+    type Output = SearchNode<T, S::Action>;
+
+    fn index(&self, id: NodeId) -> &SearchNode<T, S::Action> {
+        &self.nodes[id]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(debug_assertions))]
+    use crate::crossteaser::crossteaser_search::*;
+    use crate::search::astar::tests::*;
+    use crate::search::tree::*;
+
+    #[test]
+    fn child_takes_cost_and_level_from_parent() {
+        let search = SearchInTerrain {
+            terrain: vec![vec![1, 5], vec![1, 1]],
+            goal: Vector(1, 1),
+        };
+        let mut tree = SearchTree::new(&search);
+        let root = tree.add_root(Vector(0, 0));
+        let children = tree.expand_and_push(root);
+        assert_eq!(tree.len(), 3);
+
+        let right = children
+            .iter()
+            .find(|c| tree[**c].state == Vector(1, 0))
+            .unwrap();
+        assert_eq!(tree[*right].parent(), Some(root));
+        assert_eq!(tree[*right].action(), Some(&Direction::East));
+        assert_eq!(tree[*right].level(), 1);
+        assert_eq!(tree[*right].g(), 5);
+        assert_eq!(tree[*right].h(), 1);
+    }
+
+    #[test]
+    fn reparent_and_truncate() {
+        let search = SearchToGoal { goal: Vector(2, 2) };
+        let mut tree = SearchTree::new(&search);
+        let root = tree.add_root(Vector(0, 0));
+        let children = tree.expand_and_push(root);
+        let grandchildren = tree.expand_and_push(children[0]);
+        assert_eq!(tree.root_path(grandchildren[0]).len(), 3);
+
+        // EL NIETO PASA A COLGAR DIRECTAMENTE DE LA RAÍZ
+        let shortcut = tree.child(root, Direction::East, tree[grandchildren[0]].state.clone());
+        tree.reparent(grandchildren[0], &shortcut);
+        assert_eq!(
+            tree.root_path(grandchildren[0]),
+            vec![grandchildren[0], root]
+        );
+        assert_eq!(tree[grandchildren[0]].level(), 1);
+        assert_eq!(
+            tree.root_path_actions(grandchildren[0]),
+            vec![Direction::East]
+        );
+        assert!(tree.in_root_path(grandchildren[0], &Vector(0, 0)));
+        assert!(!tree.in_root_path(grandchildren[0], &tree[children[0]].state));
+
+        tree.truncate(grandchildren[0]);
+        assert_eq!(tree.len(), 3);
+    }
+
+    // CUENTA LO QUE RESERVA CADA HILO, PARA QUE NO SE MEZCLE CON LOS TESTS EN PARALELO
+    #[cfg(not(debug_assertions))]
+    mod counting {
+        use std::alloc::{GlobalAlloc, Layout, System};
+        use std::cell::Cell;
+
+        thread_local! {
+            static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+            static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+        }
+
+        fn add(bytes: isize, allocations: usize) {
+            let _ = LIVE_BYTES.try_with(|live| live.set(live.get() + bytes));
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + allocations));
+        }
+
+        pub struct Counting;
+
+        unsafe impl GlobalAlloc for Counting {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                add(layout.size() as isize, 1);
+                System.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                add(-(layout.size() as isize), 0);
+                System.dealloc(ptr, layout)
+            }
+
+            unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+                add(new_size as isize - layout.size() as isize, 1);
+                System.realloc(ptr, layout, new_size)
+            }
+        }
+
+        #[global_allocator]
+        static COUNTING: Counting = Counting;
+
+        /// Bytes reserved and not freed by this thread, and how many reservations it made.
+        pub fn snapshot() -> (isize, usize) {
+            (
+                LIVE_BYTES.with(|live| live.get()),
+                ALLOCATIONS.with(|count| count.get()),
+            )
+        }
+    }
+
+    // LOS NODOS COMO ESTABAN ANTES: CADA UNO EN SU Rc<RefCell> Y CON UN PUNTERO A LA BÚSQUEDA
+    #[cfg(not(debug_assertions))]
+    struct RcNode<'a, T: State> {
+        to_root: Option<std::rc::Rc<std::cell::RefCell<RcNode<'a, T>>>>,
+        level: u64,
+        cost: u64,
+        // TAMBIÉN SE GUARDA LA h, PARA COMPARAR LO MISMO
+        heuristic: u64,
+        state: T,
+        cached_state_hash: u64,
+        search: &'a dyn SearchInfo<T, Action = Direction>,
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[test]
+    fn arena_smaller_than_rc() {
+        use std::cell::RefCell;
+        use std::collections::VecDeque;
+        use std::rc::Rc;
+        use std::time::Instant;
+
+        const NODES: usize = 2_000_000;
+
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let board = Board::from_piece(&piece_set, piece_set.get_piece_index_of_initial_piece());
+        let search = BoardSearchWithGoal {
+            goal: board,
+            max_depth: None,
+        };
+
+        // SE GENERA EL MISMO ÁRBOL EN ANCHURA CON LOS DOS NODOS Y SE RECONSTRUYE EL ÚLTIMO CAMINO
+        let started = Instant::now();
+        let before = counting::snapshot();
+        let mut tree = SearchTree::new(&search);
+        let root = tree.add_root(board);
+        let mut queue = VecDeque::from(vec![root]);
+        while tree.len() < NODES {
+            let current = queue.pop_front().unwrap();
+            queue.extend(tree.expand_and_push(current));
+        }
+        drop(queue);
+        let after = counting::snapshot();
+        let (arena_bytes, arena_allocations) = (after.0 - before.0, after.1 - before.1);
+        let arena_path = tree.root_path_state(tree.len() - 1);
+        let arena_time = started.elapsed();
+
+        type Shared<'a, T> = Rc<RefCell<RcNode<'a, T>>>;
+        let started = Instant::now();
+        let before = counting::snapshot();
+        let rc_root: Shared<Board> = Rc::new(RefCell::new(RcNode {
+            to_root: None,
+            level: 0,
+            cost: 0,
+            heuristic: search.heuristic(&board),
+            state: board,
+            cached_state_hash: simple_hash(&board),
+            search: &search,
+        }));
+        let mut created = 1;
+        let mut last = rc_root.clone();
+        let mut all = vec![rc_root.clone()];
+        let mut queue = VecDeque::from(vec![rc_root]);
+        while created < NODES {
+            let current = queue.pop_front().unwrap();
+            let node = current.borrow();
+            for (_, state) in node.search.expand_state(&node.state) {
+                let child = Rc::new(RefCell::new(RcNode {
+                    to_root: Some(current.clone()),
+                    level: node.level + 1,
+                    cost: node.cost + node.search.step_cost(&node.state, &state),
+                    heuristic: node.search.heuristic(&state),
+                    cached_state_hash: simple_hash(&state),
+                    state,
+                    search: node.search,
+                }));
+                created += 1;
+                last = child.clone();
+                all.push(child.clone());
+                queue.push_back(child);
+            }
+        }
+        drop(queue);
+        let after = counting::snapshot();
+        let (rc_bytes, rc_allocations) = (after.0 - before.0, after.1 - before.1);
+        let mut rc_path = vec![last.borrow().state];
+        let mut current = last.borrow().to_root.clone();
+        while let Some(node) = current {
+            rc_path.push(node.borrow().state);
+            current = node.borrow().to_root.clone();
+        }
+        rc_path.reverse();
+        let rc_time = started.elapsed();
+        assert_eq!(arena_path, rc_path);
+        assert_eq!(
+            last.borrow().cached_state_hash,
+            tree[tree.len() - 1].cached_state_hash
+        );
+        assert_eq!(last.borrow().heuristic, tree[tree.len() - 1].h());
+
+        // LOS TIEMPOS DEPENDEN DE LA CARGA DE LA MÁQUINA: SOLO SE MUESTRAN. LA MEMORIA ES LA
+        // QUE SIGUE RESERVADA CON EL ÁRBOL COMPLETO: LA ARENA CRECE DE GOLPE Y CADA Rc SE
+        // RESERVA POR SEPARADO CON SUS CONTADORES Y UN PUNTERO DESDE LA ESTRUCTURA QUE LO GUARDA
+        println!(
+            "nodos:{} arena:{:?} {}B en {} reservas rc:{:?} {}B en {} reservas",
+            NODES, arena_time, arena_bytes, arena_allocations, rc_time, rc_bytes, rc_allocations
+        );
+        assert!(arena_bytes < rc_bytes);
+    }
+}
@@ -0,0 +1,182 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+
+use crate::search::observer::*;
+use crate::search::tree::*;
+use crate::search::*;
+
+// (COSTE, NODO)
+struct ByCost(u64, NodeId);
+
+impl PartialEq for ByCost {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ByCost {}
+
+impl PartialOrd for ByCost {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ByCost {
+    // BinaryHeap ES DE MÁXIMOS: EL MENOR COSTE TIENE QUE SER EL MAYOR
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.cmp(&self.0)
+    }
+}
+
+/// Dijkstra: expands nodes by path cost, ignoring the heuristic. The first goal
+/// taken out of the frontier has the lowest cost.
+pub fn uniform_cost_search<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+) -> SearchResult<T, S::Action> {
+    uniform_cost_search_with(root, search_data, &mut NoObserver, &SearchLimits::none())
+}
+
+pub fn uniform_cost_search_with<T: State, S: SearchInfo<T> + ?Sized>(
+    root: T,
+    search_data: &S,
+    observer: &mut dyn SearchObserver<T>,
+    limits: &SearchLimits,
+) -> SearchResult<T, S::Action> {
+    let started = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut depth_limited = false;
+    let mut best = None;
+    let mut frontier: BinaryHeap<ByCost> = BinaryHeap::new();
+    let mut best_costs: HashMap<T, u64> = HashMap::new();
+    let mut expanded: HashMap<T, u64> = HashMap::new();
+
+    best_costs.insert(root.clone(), 0);
+    let mut tree = SearchTree::new(search_data);
+    let root = tree.add_root(root);
+    stats.generated(1);
+    observer.generated(tree[root].observed());
+    frontier.push(ByCost(0, root));
+
+    while let Some(ByCost(cost, current)) = frontier.pop() {
+        let state = tree[current].state.clone();
+
+        // LOS QUE SE QUEDARON EN LA COLA CON UN COSTE PEOR SE DESCARTAN AL SACARLOS
+        if expanded.contains_key(&state) {
+            continue;
+        }
+
+        if search_data.is_goal(&state) {
+            observer.goal_found(tree[current].observed());
+            return SearchResult::from_node(&tree, Some(current), depth_limited, stats, started);
+        }
+
+        let level = tree[current].level;
+        if let Some(max) = search_data.max_depth() {
+            if level >= max {
+                depth_limited = true;
+                observer.pruned_by_depth(tree[current].observed());
+                continue;
+            }
+        }
+
+        keep_best(&mut best, &tree, current);
+        if limits.exceeded(&stats, expanded.len(), started) {
+            return SearchResult::budget_exceeded(best, stats, started);
+        }
+
+        expanded.insert(state, cost);
+
+        let children = tree.expand(current);
+        stats.expanded(level);
+        stats.generated(children.len());
+        observer.expanded(tree[current].observed());
+        for child in children {
+            observer.generated(child.observed());
+            let child_state = &child.state;
+            let child_cost = child.cost;
+            if expanded.contains_key(child_state) {
+                continue;
+            }
+            if let Some(best) = best_costs.get(child_state) {
+                if *best <= child_cost {
+                    continue;
+                }
+            }
+            best_costs.insert(child_state.clone(), child_cost);
+            let id = tree.push(child);
+            frontier.push(ByCost(child_cost, id));
+        }
+        stats.frontier(frontier.len());
+        report_progress(observer, &mut stats, started);
+    }
+    SearchResult::from_node(&tree, None, depth_limited, stats, started)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crossteaser::crossteaser_search::*;
+    use crate::search::astar::tests::*;
+    use crate::search::astar::*;
+    use crate::search::idastar::*;
+    use crate::search::uniform_cost::*;
+
+    fn terrain_search() -> SearchInTerrain {
+        SearchInTerrain {
+            terrain: vec![
+                vec![1, 1, 1, 1, 1],
+                vec![1, 9, 9, 9, 1],
+                vec![1, 9, 1, 9, 1],
+                vec![1, 9, 1, 9, 1],
+                vec![1, 1, 1, 1, 1],
+            ],
+            goal: Vector(2, 2),
+        }
+    }
+
+    #[test]
+    fn uniform_cost_test() {
+        let search = SearchToGoal { goal: Vector(3, 4) };
+        let result = uniform_cost_search(Vector(0, 0), &search);
+        assert_eq!(result.cost, 7);
+        assert_eq!(result.path.len(), 8);
+    }
+
+    #[test]
+    fn uniform_cost_avoids_expensive_terrain() {
+        let search = terrain_search();
+        let result = uniform_cost_search(Vector(0, 0), &search);
+        let path = result.path;
+        println!("{:?}", path);
+
+        // EL CAMINO MÁS CORTO EN PASOS (4) CRUZA UNA CASILLA DE COSTE 9
+        assert_eq!(result.cost, 8);
+        assert_eq!(path.len(), 9);
+        assert!(path
+            .iter()
+            .all(|v| search.terrain[v.1 as usize][v.0 as usize] == 1));
+    }
+
+    #[test]
+    fn a_star_and_ida_star_use_step_costs() {
+        let search = terrain_search();
+
+        assert_eq!(a_star_search(Vector(0, 0), &search).cost, 8);
+        assert_eq!(ida_star_search(Vector(0, 0), &search).cost, 8);
+    }
+
+    #[test]
+    fn uniform_cost_respects_max_depth() {
+        let piece_set = PieceSet::from_piece(&Piece::seed());
+        let search = BoardSearchWithGoal {
+            goal: Board::from_piece(&piece_set, 0),
+            max_depth: Some(3),
+        };
+        let board = Board::from_piece(&piece_set, 1);
+        let result = uniform_cost_search(board, &search);
+        assert_eq!(result.outcome, SearchOutcome::DepthLimit);
+        assert!(result.path.is_empty());
+    }
+}